@@ -17,10 +17,16 @@ use bevy_ggrs::ggrs::Config;
 use bevy_ggrs::prelude::*;
 
 use crate::anim::{sprite_sheet_animator_system, SpriteSheetAnimator};
-use crate::body::{PhysicsBody, PhysicsBodyHandle, PhysicsBodyOptions, PhysicsBodyVelocity};
+use crate::body::{PhysicsBody, PhysicsBodyHandle, PhysicsBodyOptions, PhysicsBodyVelocity, PhysicsForce, PhysicsImpulse, PhysicsTeleport};
 use crate::clock::{ttl_system, TimeToLive};
-use crate::collider::{PhysicsCollider, PhysicsColliderHandle, PhysicsColliderOptions};
-use crate::controller::PhysicsCharacterController;
+use crate::collider::{PhysicsChildColliderHandles, PhysicsChildColliders, PhysicsCollider, PhysicsColliderHandle, PhysicsColliderOptions};
+use crate::controller::{PhysicsCharacterController, PhysicsCharacterControllerOptions};
+use crate::debug::PhysicsDebug;
+use crate::event::RollbackEventAppExt;
+use crate::events::{PhysicsCollisionEvent, PhysicsContactForceEvent, PhysicsSensorEvent};
+use crate::gravity::{GravityWell, PhysicsGravityZone};
+use crate::joint::{PhysicsJoint, PhysicsJointHandle};
+use crate::material::{PhysicsColliderMaterial, PhysicsMaterial};
 use crate::physics::*;
 
 pub trait AddCoreAppExt {
@@ -37,6 +43,15 @@ impl AddCoreAppExt for App {
         self.add_plugins(GgrsPlugin::<T>::default())
             .add_systems(ReadInputs, input_system)
             .set_rollback_schedule_fps(fps)
+            .init_resource::<PhysicsDebug>()
+            .init_resource::<PhysicsWorkspace>()
+            .init_resource::<PhysicsRemovals>()
+            .init_asset::<PhysicsMaterial>()
+            .init_asset::<PhysicsCharacterControllerOptions>()
+            //
+            .rollback_events::<PhysicsSensorEvent>()
+            .rollback_events::<PhysicsCollisionEvent>()
+            .rollback_events::<PhysicsContactForceEvent>()
             //
             .checksum_resource_with_hash::<Physics>()
             .checksum_component_with_hash::<TimeToLive>()
@@ -44,22 +59,46 @@ impl AddCoreAppExt for App {
             .checksum_component_with_hash::<PhysicsBodyHandle>()
             .checksum_component_with_hash::<PhysicsBodyOptions>()
             .checksum_component_with_hash::<PhysicsBodyVelocity>()
+            .checksum_component_with_hash::<PhysicsImpulse>()
+            .checksum_component_with_hash::<PhysicsForce>()
+            .checksum_component_with_hash::<PhysicsTeleport>()
             .checksum_component_with_hash::<PhysicsCollider>()
             .checksum_component_with_hash::<PhysicsColliderHandle>()
             .checksum_component_with_hash::<PhysicsColliderOptions>()
+            .checksum_component_with_hash::<PhysicsColliderMaterial>()
+            .checksum_component_with_hash::<PhysicsChildColliders>()
+            .checksum_component_with_hash::<PhysicsChildColliderHandles>()
+            .checksum_component_with_hash::<PhysicsJoint>()
+            .checksum_component_with_hash::<PhysicsJointHandle>()
             .checksum_component_with_hash::<PhysicsCharacterController>()
+            .checksum_component_with_hash::<PhysicsGravityZone>()
+            .checksum_component_with_hash::<GravityWell>()
             //
             .rollback_resource_with_copy::<Scaler>()
             .rollback_resource_with_clone::<Physics>()
+            .update_resource_with_map_entities::<Physics>()
+            .rollback_resource_with_clone::<PhysicsRemovals>()
+            .update_resource_with_map_entities::<PhysicsRemovals>()
             .rollback_component_with_copy::<TimeToLive>()
             .rollback_component_with_copy::<PhysicsBody>()
             .rollback_component_with_copy::<PhysicsBodyHandle>()
             .rollback_component_with_copy::<PhysicsBodyOptions>()
             .rollback_component_with_copy::<PhysicsBodyVelocity>()
-            .rollback_component_with_copy::<PhysicsCollider>()
+            .rollback_component_with_copy::<PhysicsImpulse>()
+            .rollback_component_with_copy::<PhysicsForce>()
+            .rollback_component_with_copy::<PhysicsTeleport>()
+            .rollback_component_with_clone::<PhysicsCollider>()
             .rollback_component_with_copy::<PhysicsColliderHandle>()
             .rollback_component_with_copy::<PhysicsColliderOptions>()
+            .rollback_component_with_clone::<PhysicsColliderMaterial>()
+            .rollback_component_with_clone::<PhysicsChildColliders>()
+            .rollback_component_with_clone::<PhysicsChildColliderHandles>()
+            .rollback_component_with_copy::<PhysicsJoint>()
+            .update_component_with_map_entities::<PhysicsJoint>()
+            .rollback_component_with_copy::<PhysicsJointHandle>()
             .rollback_component_with_copy::<PhysicsCharacterController>()
+            .rollback_component_with_copy::<PhysicsGravityZone>()
+            .rollback_component_with_copy::<GravityWell>()
             .rollback_component_with_clone::<Sprite>()
             .rollback_component_with_clone::<SpriteSheetAnimator>();
 
@@ -6,6 +6,8 @@ use bevy_asset_loader::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::anim::SpriteSheetAnimation;
+use crate::physics::controller::PhysicsCharacterControllerOptions;
+use crate::physics::material::PhysicsMaterial;
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 enum Asset {
@@ -13,6 +15,8 @@ enum Asset {
     Sound(SoundAsset),
     TextureAtlasLayout(TextureAtlasLayoutAsset),
     SpriteSheetAnimation(SpriteSheetAnimationAsset),
+    PhysicsCharacterControllerOptions(PhysicsCharacterControllerOptions),
+    PhysicsMaterial(PhysicsMaterial),
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -63,6 +67,8 @@ impl DynamicAsset for CoreDynamicAsset {
             Asset::SpriteSheetAnimation(SpriteSheetAnimationAsset { speed, start, finish, repeat }) => asset_server
                 .add(SpriteSheetAnimation { speed, start, finish, repeat })
                 .untyped(),
+            Asset::PhysicsCharacterControllerOptions(options) => asset_server.add(options).untyped(),
+            Asset::PhysicsMaterial(material) => asset_server.add(material).untyped(),
         };
 
         match self {
@@ -105,6 +111,8 @@ impl DynamicAsset for CoreDynamicAsset {
             Asset::SpriteSheetAnimation(SpriteSheetAnimationAsset { speed, start, finish, repeat }) => asset_server
                 .add(SpriteSheetAnimation { speed, start, finish, repeat })
                 .untyped(),
+            Asset::PhysicsCharacterControllerOptions(options) => asset_server.add(options).untyped(),
+            Asset::PhysicsMaterial(material) => asset_server.add(material).untyped(),
         };
         match self {
             CoreDynamicAsset::Asset(asset) => Ok(DynamicAssetType::Single(build_asset(
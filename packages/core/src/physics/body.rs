@@ -1,7 +1,10 @@
+use bevy::ecs::component::HookContext;
+use bevy::ecs::world::DeferredWorld;
 use bevy::prelude::*;
 use derivative::Derivative;
 use rapier2d::prelude::*;
 
+use crate::physics::PhysicsRemovals;
 use crate::utilities::maths::*;
 use crate::Scaler;
 
@@ -38,8 +41,50 @@ pub struct PhysicsBodyVelocity {
 }
 
 #[derive(Hash, Copy, Clone, Component)]
+#[component(on_remove = on_remove_body_handle)]
 pub struct PhysicsBodyHandle(pub(crate) RigidBodyHandle);
 
+/// Impulse applied once during the next physics update, the component is then removed.
+/// It adds to the current motion of the body, unlike [`PhysicsBodyVelocity`] which replaces it.
+#[derive(Copy, Clone, Component, Derivative)]
+#[derivative(Hash)]
+pub struct PhysicsImpulse {
+    /// Linear impulse in pixel units, or velocity change in pixels per second.
+    #[derivative(Hash = "ignore")]
+    pub linear: Vec2,
+    /// Angular impulse in pixel units, or velocity change in radians per second.
+    #[derivative(Hash = "ignore")]
+    pub angular: f32,
+    /// Ignores the mass of the body, the impulse is added to its velocity as is.
+    pub velocity_change: bool,
+}
+
+/// Force and torque applied at every step while the component is present, in pixel units.
+#[derive(Copy, Clone, Default, Component, Derivative)]
+#[derivative(Hash)]
+#[component(on_remove = on_remove_force)]
+pub struct PhysicsForce {
+    #[derivative(Hash = "ignore")]
+    pub force: Vec2,
+    #[derivative(Hash = "ignore")]
+    pub torque: f32,
+}
+
+/// Moves the body of this entity during the next physics update, the component is then removed.
+/// Writing the transform has no effect as it is overwritten by the body position every frame.
+#[derive(Copy, Clone, Component, Derivative)]
+#[derivative(Hash)]
+pub struct PhysicsTeleport {
+    /// Translation in pixels.
+    #[derivative(Hash = "ignore")]
+    pub translation: Vec2,
+    /// Rotation in radians, the current one is kept when none.
+    #[derivative(Hash = "ignore")]
+    pub rotation: Option<f32>,
+    pub reset_velocity: bool,
+    pub wake_up: bool,
+}
+
 impl PhysicsBody {
     pub(crate) fn build(&self, scaler: &Scaler, transform: &Transform) -> RigidBody {
         let rotation = transform.rotation.to_euler(EulerRot::ZYX).0;
@@ -47,29 +92,28 @@ impl PhysicsBody {
             .pixels_to_meters(transform.translation)
             .to_physics();
 
+        RigidBodyBuilder::new(self.body_type())
+            .rotation(rotation)
+            .translation(translation)
+            .build()
+    }
+
+    pub(crate) fn body_type(&self) -> RigidBodyType {
         match self {
-            PhysicsBody::Fixed => RigidBodyBuilder::fixed()
-                .rotation(rotation)
-                .translation(translation)
-                .build(),
-            PhysicsBody::Dynamic => RigidBodyBuilder::dynamic()
-                .rotation(rotation)
-                .translation(translation)
-                .build(),
-            PhysicsBody::KinematicPositionBased => RigidBodyBuilder::kinematic_position_based()
-                .rotation(rotation)
-                .translation(translation)
-                .build(),
-            PhysicsBody::KinematicVelocityBased => RigidBodyBuilder::kinematic_velocity_based()
-                .rotation(rotation)
-                .translation(translation)
-                .build(),
+            PhysicsBody::Fixed => RigidBodyType::Fixed,
+            PhysicsBody::Dynamic => RigidBodyType::Dynamic,
+            PhysicsBody::KinematicPositionBased => RigidBodyType::KinematicPositionBased,
+            PhysicsBody::KinematicVelocityBased => RigidBodyType::KinematicVelocityBased,
         }
     }
 
     pub(crate) fn apply_options(&self, scaler: &Scaler, body: &mut RigidBody, options: &PhysicsBodyOptions) {
         let wake_up = true;
 
+        // Switching type keeps the position and velocity, a dynamic body starts from the motion of the kinematic one.
+        if body.body_type() != self.body_type() {
+            body.set_body_type(self.body_type(), wake_up);
+        }
         match options.sleep {
             None => (),
             Some(true) => body.sleep(),
@@ -107,6 +151,121 @@ impl PhysicsBodyHandle {
     }
 }
 
+fn on_remove_body_handle(mut world: DeferredWorld, HookContext { entity, .. }: HookContext) {
+    let Some(body_handle) = world.get::<PhysicsBodyHandle>(entity).copied() else {
+        return;
+    };
+
+    if let Some(mut removals) = world.get_resource_mut::<PhysicsRemovals>() {
+        removals.bodies.push((entity, body_handle.handle()));
+    }
+}
+
+impl PhysicsImpulse {
+    pub fn new(linear: Vec2, angular: f32) -> Self {
+        Self {
+            linear,
+            angular,
+            velocity_change: false,
+        }
+    }
+
+    pub fn from_velocity_change(linear: Vec2, angular: f32) -> Self {
+        Self {
+            linear,
+            angular,
+            velocity_change: true,
+        }
+    }
+
+    pub(crate) fn apply(&self, scaler: &Scaler, body: &mut RigidBody) {
+        let wake_up = true;
+        let linear = scaler.pixels_to_meters(self.linear).to_physics();
+
+        match self.velocity_change {
+            true => {
+                body.set_linvel(body.linvel() + linear, wake_up);
+                body.set_angvel(body.angvel() + self.angular, wake_up);
+            }
+            false => {
+                body.apply_impulse(linear, wake_up);
+                body.apply_torque_impulse(scaler.pixels_to_meters(scaler.pixels_to_meters(self.angular)), wake_up);
+            }
+        }
+    }
+}
+
+impl PhysicsForce {
+    pub fn from_force(force: Vec2) -> Self {
+        Self { force, ..default() }
+    }
+
+    pub fn from_torque(torque: f32) -> Self {
+        Self { torque, ..default() }
+    }
+
+    /// Replaces the forces of the body, which rapier keeps across steps until they are reset.
+    pub(crate) fn apply(&self, scaler: &Scaler, body: &mut RigidBody) {
+        let wake_up = true;
+
+        body.reset_forces(false);
+        body.reset_torques(false);
+        body.add_force(scaler.pixels_to_meters(self.force).to_physics(), wake_up);
+        body.add_torque(scaler.pixels_to_meters(scaler.pixels_to_meters(self.torque)), wake_up);
+    }
+}
+
+fn on_remove_force(mut world: DeferredWorld, HookContext { entity, .. }: HookContext) {
+    let Some(body_handle) = world.get::<PhysicsBodyHandle>(entity).copied() else {
+        return;
+    };
+
+    if let Some(mut removals) = world.get_resource_mut::<PhysicsRemovals>() {
+        removals.forces.push((entity, body_handle.handle()));
+    }
+}
+
+impl PhysicsTeleport {
+    pub fn new(translation: Vec2) -> Self {
+        Self {
+            translation,
+            rotation: None,
+            reset_velocity: true,
+            wake_up: true,
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = Some(rotation);
+        self
+    }
+
+    pub fn keep_velocity(mut self) -> Self {
+        self.reset_velocity = false;
+        self
+    }
+
+    pub(crate) fn apply(&self, scaler: &Scaler, body: &mut RigidBody) {
+        let rotation = self
+            .rotation
+            .unwrap_or_else(|| body.rotation().angle());
+
+        body.set_position(
+            Isometry::new(
+                scaler
+                    .pixels_to_meters(self.translation)
+                    .to_physics(),
+                rotation,
+            ),
+            self.wake_up,
+        );
+        if self.reset_velocity {
+            body.set_linvel(Vector::zeros(), self.wake_up);
+            body.set_angvel(0.0, self.wake_up);
+        }
+    }
+}
+
 impl PhysicsBodyOptions {
     pub fn from_gravity_scale(gravity_scale: f32) -> Self {
         Self { gravity_scale, ..default() }
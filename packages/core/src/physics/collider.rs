@@ -1,31 +1,68 @@
+use std::hash::Hash;
+
+use bevy::ecs::component::HookContext;
+use bevy::ecs::world::DeferredWorld;
 use bevy::prelude::*;
 use derivative::Derivative;
+use rapier2d::math::Real;
+use rapier2d::na::DVector;
+use rapier2d::parry::shape::TypedShape;
 use rapier2d::prelude::*;
 
+use crate::physics::material::{PhysicsCombineRule, PhysicsMaterial};
+use crate::physics::PhysicsRemovals;
+use crate::utilities::hash::f32_hasher;
+use crate::utilities::maths::*;
 use crate::Scaler;
 
-#[derive(Copy, Clone, Component, Derivative)]
-#[derivative(Hash)]
+#[derive(Clone, Component)]
 pub enum PhysicsCollider {
     Circle {
-        #[derivative(Hash = "ignore")]
         radius: f32,
     },
     Rectangle {
-        #[derivative(Hash = "ignore")]
         width: f32,
-        #[derivative(Hash = "ignore")]
         height: f32,
     },
+    /// Vertical capsule, the height includes both rounded ends.
+    Capsule {
+        radius: f32,
+        height: f32,
+    },
+    Segment {
+        a: Vec2,
+        b: Vec2,
+    },
+    /// Convex hull of the given points, a polyline through them when they are fewer than 3 or collinear.
+    ConvexPolygon {
+        points: Vec<Vec2>,
+    },
+    /// Falls back to a point when there are fewer than 2 points.
+    Polyline {
+        points: Vec<Vec2>,
+    },
+    /// Heights are evenly spaced along the width, centered on the collider origin.
+    /// A single height is a flat segment across the width.
+    Heightfield {
+        width: f32,
+        heights: Vec<f32>,
+    },
 }
 
 #[derive(Copy, Clone, Component, Derivative)]
 #[derivative(Hash)]
 pub struct PhysicsColliderOptions {
+    pub sensor: bool,
+    /// Character controllers only collide with it from above, see [`PhysicsCharacterController::drop_through`].
+    ///
+    /// [`PhysicsCharacterController::drop_through`]: crate::physics::controller::PhysicsCharacterController::drop_through
+    pub one_way: bool,
     #[derivative(Hash = "ignore")]
     pub friction: f32,
     #[derivative(Hash = "ignore")]
     pub restitution: f32,
+    pub friction_combine_rule: PhysicsCombineRule,
+    pub restitution_combine_rule: PhysicsCombineRule,
     #[derivative(Hash = "ignore")]
     pub active_events: ActiveEvents,
     pub collision_groups: InteractionGroups,
@@ -33,29 +70,169 @@ pub struct PhysicsColliderOptions {
 }
 
 #[derive(Hash, Copy, Clone, Component)]
+#[component(on_remove = on_remove_collider_handle)]
 pub struct PhysicsColliderHandle(pub(crate) ColliderHandle);
 
+/// Additional collider attached to the body of an entity, offset from the body origin.
+#[derive(Clone, Derivative)]
+#[derivative(Hash)]
+pub struct PhysicsChildCollider {
+    pub collider: PhysicsCollider,
+    pub options: PhysicsColliderOptions,
+    /// Replaces the friction and restitution of the options, like [`PhysicsColliderMaterial`] on the main collider.
+    ///
+    /// [`PhysicsColliderMaterial`]: crate::physics::material::PhysicsColliderMaterial
+    pub material: Option<Handle<PhysicsMaterial>>,
+    #[derivative(Hash = "ignore")]
+    pub rotation: f32,
+    #[derivative(Hash = "ignore")]
+    pub translation: Vec2,
+}
+
+#[derive(Hash, Clone, Default, Component)]
+pub struct PhysicsChildColliders(pub Vec<PhysicsChildCollider>);
+
+#[derive(Hash, Clone, Default, Component)]
+#[component(on_remove = on_remove_child_collider_handles)]
+pub struct PhysicsChildColliderHandles(pub(crate) Vec<ColliderHandle>);
+
 impl PhysicsCollider {
     pub(crate) fn build(&self, scaler: &Scaler) -> Collider {
+        ColliderBuilder::new(self.shape(scaler)).build()
+    }
+
+    pub(crate) fn shape(&self, scaler: &Scaler) -> SharedShape {
+        let to_point = |point: &Vec2| Point::from(scaler.pixels_to_meters(*point).to_physics());
+
         match self {
-            Self::Circle { radius } => ColliderBuilder::ball(scaler.pixels_to_meters(radius)).build(),
-            Self::Rectangle { width, height } => ColliderBuilder::cuboid(
+            Self::Circle { radius } => SharedShape::ball(scaler.pixels_to_meters(radius)),
+            Self::Rectangle { width, height } => SharedShape::cuboid(
                 scaler.pixels_to_meters(width) / 2.0,
                 scaler.pixels_to_meters(height) / 2.0,
-            )
-            .build(),
+            ),
+            Self::Capsule { radius, height } => SharedShape::capsule_y(
+                scaler.pixels_to_meters(height / 2.0 - radius).max(0.0),
+                scaler.pixels_to_meters(radius),
+            ),
+            Self::Segment { a, b } => SharedShape::segment(to_point(a), to_point(b)),
+            Self::ConvexPolygon { points } => {
+                let points = points
+                    .iter()
+                    .map(to_point)
+                    .collect::<Vec<_>>();
+
+                match points.len() {
+                    0..3 => polyline(points),
+                    _ => SharedShape::convex_hull(&points).unwrap_or_else(|| polyline(points)),
+                }
+            }
+            Self::Polyline { points } => polyline(
+                points
+                    .iter()
+                    .map(to_point)
+                    .collect(),
+            ),
+            Self::Heightfield { width, heights } if heights.len() < 2 => {
+                let height = heights.first().copied().unwrap_or_default();
+
+                SharedShape::segment(
+                    to_point(&Vec2::new(-width / 2.0, height)),
+                    to_point(&Vec2::new(width / 2.0, height)),
+                )
+            }
+            Self::Heightfield { width, heights } => SharedShape::heightfield(
+                DVector::from_iterator(
+                    heights.len(),
+                    heights
+                        .iter()
+                        .map(|height| scaler.pixels_to_meters(height)),
+                ),
+                vector![scaler.pixels_to_meters(width), 1.0],
+            ),
+        }
+    }
+
+    /// Replaces the shape of the collider when it no longer matches this description.
+    /// Shapes are compared by value, so peers that resimulated different frames still make the same changes.
+    pub(crate) fn apply_shape(&self, scaler: &Scaler, collider: &mut Collider) {
+        let shape = self.shape(scaler);
+
+        if !shape_eq(collider.shape(), &*shape) {
+            collider.set_shape(shape);
         }
     }
 
     pub(crate) fn apply_options(&self, _scaler: &Scaler, collider: &mut Collider, options: &PhysicsColliderOptions) {
+        collider.set_sensor(options.sensor);
         collider.set_friction(options.friction);
         collider.set_restitution(options.restitution);
+        collider.set_friction_combine_rule(options.friction_combine_rule.into());
+        collider.set_restitution_combine_rule(options.restitution_combine_rule.into());
         collider.set_active_events(options.active_events);
         collider.set_collision_groups(options.collision_groups);
         collider.set_active_collision_types(options.active_collision_types);
     }
 }
 
+/// Parry panics on polylines without segments, fewer points give a degenerate segment instead.
+fn polyline(points: Vec<Point<Real>>) -> SharedShape {
+    match points.as_slice() {
+        [] => SharedShape::segment(Point::origin(), Point::origin()),
+        [point] => SharedShape::segment(*point, *point),
+        _ => SharedShape::polyline(points, None),
+    }
+}
+
+fn shape_eq(a: &dyn Shape, b: &dyn Shape) -> bool {
+    match (a.as_typed_shape(), b.as_typed_shape()) {
+        (TypedShape::Ball(a), TypedShape::Ball(b)) => a.radius == b.radius,
+        (TypedShape::Cuboid(a), TypedShape::Cuboid(b)) => a.half_extents == b.half_extents,
+        (TypedShape::Capsule(a), TypedShape::Capsule(b)) => a.segment == b.segment && a.radius == b.radius,
+        (TypedShape::Segment(a), TypedShape::Segment(b)) => a == b,
+        (TypedShape::ConvexPolygon(a), TypedShape::ConvexPolygon(b)) => a.points() == b.points(),
+        (TypedShape::Polyline(a), TypedShape::Polyline(b)) => a.vertices() == b.vertices() && a.indices() == b.indices(),
+        (TypedShape::HeightField(a), TypedShape::HeightField(b)) => a.heights() == b.heights() && a.scale() == b.scale(),
+        _ => false,
+    }
+}
+
+impl Hash for PhysicsCollider {
+    fn hash<H: std::hash::Hasher>(&self, mut state: &mut H) {
+        std::mem::discriminant(self).hash(&mut state);
+        match self {
+            Self::Circle { radius } => f32_hasher(*radius, &mut state),
+            Self::Rectangle { width, height } => {
+                f32_hasher(*width, &mut state);
+                f32_hasher(*height, &mut state);
+            }
+            Self::Capsule { radius, height } => {
+                f32_hasher(*radius, &mut state);
+                f32_hasher(*height, &mut state);
+            }
+            Self::Segment { a, b } => {
+                f32_hasher(a.x, &mut state);
+                f32_hasher(a.y, &mut state);
+                f32_hasher(b.x, &mut state);
+                f32_hasher(b.y, &mut state);
+            }
+            Self::ConvexPolygon { points } | Self::Polyline { points } => {
+                points.len().hash(&mut state);
+                for point in points {
+                    f32_hasher(point.x, &mut state);
+                    f32_hasher(point.y, &mut state);
+                }
+            }
+            Self::Heightfield { width, heights } => {
+                f32_hasher(*width, &mut state);
+                heights.len().hash(&mut state);
+                for height in heights {
+                    f32_hasher(*height, &mut state);
+                }
+            }
+        }
+    }
+}
+
 impl PhysicsColliderHandle {
     #[inline(always)]
     pub fn handle(&self) -> ColliderHandle {
@@ -63,6 +240,84 @@ impl PhysicsColliderHandle {
     }
 }
 
+impl PhysicsChildCollider {
+    pub fn new(collider: PhysicsCollider, options: PhysicsColliderOptions, translation: Vec2, rotation: f32) -> Self {
+        Self {
+            collider,
+            options,
+            material: None,
+            rotation,
+            translation,
+        }
+    }
+
+    pub fn with_material(mut self, material: Handle<PhysicsMaterial>) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    pub(crate) fn build(&self, scaler: &Scaler) -> Collider {
+        let mut collider = self.collider.build(scaler);
+
+        collider.set_position(self.position(scaler));
+        collider
+    }
+
+    pub(crate) fn apply_shape(&self, scaler: &Scaler, collider: &mut Collider) {
+        self.collider.apply_shape(scaler, collider);
+    }
+
+    /// Moves the collider relative to its body when the translation or rotation changed.
+    pub(crate) fn apply_position(&self, scaler: &Scaler, collider: &mut Collider) {
+        let position = self.position(scaler);
+
+        if collider.position_wrt_parent() != Some(&position) {
+            collider.set_position_wrt_parent(position);
+        }
+    }
+
+    fn position(&self, scaler: &Scaler) -> Isometry<Real> {
+        Isometry::new(
+            scaler
+                .pixels_to_meters(self.translation)
+                .to_physics(),
+            self.rotation,
+        )
+    }
+}
+
+impl PhysicsChildColliderHandles {
+    #[inline(always)]
+    pub fn handles(&self) -> &[ColliderHandle] {
+        &self.0
+    }
+}
+
+fn on_remove_collider_handle(mut world: DeferredWorld, HookContext { entity, .. }: HookContext) {
+    let Some(collider_handle) = world.get::<PhysicsColliderHandle>(entity).copied() else {
+        return;
+    };
+
+    if let Some(mut removals) = world.get_resource_mut::<PhysicsRemovals>() {
+        removals.colliders.push((entity, collider_handle.handle()));
+    }
+}
+
+fn on_remove_child_collider_handles(mut world: DeferredWorld, HookContext { entity, .. }: HookContext) {
+    let Some(child_collider_handles) = world.get::<PhysicsChildColliderHandles>(entity).cloned() else {
+        return;
+    };
+
+    if let Some(mut removals) = world.get_resource_mut::<PhysicsRemovals>() {
+        removals.colliders.extend(
+            child_collider_handles
+                .handles()
+                .iter()
+                .map(|handle| (entity, *handle)),
+        );
+    }
+}
+
 impl PhysicsColliderOptions {
     pub fn from_friction(friction: f32) -> Self {
         Self { friction, ..default() }
@@ -72,16 +327,51 @@ impl PhysicsColliderOptions {
         Self { restitution, ..default() }
     }
 
+    pub fn from_material(material: &PhysicsMaterial) -> Self {
+        Self {
+            friction: material.friction,
+            restitution: material.restitution,
+            friction_combine_rule: material.friction_combine_rule,
+            restitution_combine_rule: material.restitution_combine_rule,
+            ..default()
+        }
+    }
+
     pub fn from_collision_groups(collision_groups: InteractionGroups) -> Self {
         Self { collision_groups, ..default() }
     }
+
+    pub fn from_one_way(collision_groups: InteractionGroups) -> Self {
+        Self {
+            one_way: true,
+            collision_groups,
+            ..default()
+        }
+    }
+
+    /// Sensor reporting enter/exit events against every kind of body.
+    pub fn from_sensor(collision_groups: InteractionGroups) -> Self {
+        Self {
+            sensor: true,
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            collision_groups,
+            active_collision_types: ActiveCollisionTypes::all(),
+            ..default()
+        }
+    }
 }
 
 impl Default for PhysicsColliderOptions {
     fn default() -> Self {
+        let material = PhysicsMaterial::default();
+
         Self {
-            friction: 1.0,
-            restitution: 0.1,
+            sensor: false,
+            one_way: false,
+            friction: material.friction,
+            restitution: material.restitution,
+            friction_combine_rule: material.friction_combine_rule,
+            restitution_combine_rule: material.restitution_combine_rule,
             active_events: default(),
             collision_groups: default(),
             active_collision_types: default(),
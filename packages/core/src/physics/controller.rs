@@ -1,23 +1,108 @@
+use std::f32::consts::FRAC_PI_4;
+use std::time::Duration;
+
 use bevy::prelude::*;
 use derivative::Derivative;
-use rapier2d::control::{CharacterCollision, EffectiveCharacterMovement, KinematicCharacterController};
+use rapier2d::control::{CharacterAutostep, CharacterCollision, CharacterLength, EffectiveCharacterMovement, KinematicCharacterController};
+use rapier2d::math::{Real, UnitVector, Vector};
+use rapier2d::parry::query::ShapeCastStatus;
+use rapier2d::prelude::RigidBodyHandle;
+use serde::{Deserialize, Serialize};
 
+use crate::physics::material::PhysicsMaterial;
+use crate::physics::Physics;
 use crate::utilities::maths::*;
+use crate::Scaler;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize, Derivative)]
+#[derivative(Hash)]
+pub enum PhysicsCharacterLength {
+    /// Length in pixels.
+    Absolute(#[derivative(Hash = "ignore")] f32),
+    /// Length relative to the size of the character collider.
+    Relative(#[derivative(Hash = "ignore")] f32),
+}
 
-#[derive(Hash, Copy, Clone, Default)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize, Derivative)]
+#[derivative(Hash)]
+#[serde(default)]
+pub struct PhysicsCharacterAutostep {
+    pub max_height: PhysicsCharacterLength,
+    pub min_width: PhysicsCharacterLength,
+    pub include_dynamic_bodies: bool,
+}
+
+/// Tuning of the character controller, defaults match the ones of rapier.
+/// Angles are in radians.
+#[derive(Asset, TypePath, Copy, Clone, Debug, PartialEq, Serialize, Deserialize, Derivative)]
+#[derivative(Hash)]
+#[serde(default)]
+pub struct PhysicsCharacterControllerOptions {
+    /// Gap kept between the character and the obstacles.
+    pub offset: PhysicsCharacterLength,
+    pub slide: bool,
+    pub autostep: Option<PhysicsCharacterAutostep>,
+    #[derivative(Hash = "ignore")]
+    pub max_slope_climb_angle: f32,
+    #[derivative(Hash = "ignore")]
+    pub min_slope_slide_angle: f32,
+    pub snap_to_ground: Option<PhysicsCharacterLength>,
+    #[derivative(Hash = "ignore")]
+    pub normal_nudge_factor: f32,
+    /// Mass of the character when pushing dynamic bodies, they are not pushed when none.
+    #[derivative(Hash = "ignore")]
+    pub push_mass: Option<f32>,
+}
+
+#[derive(Copy, Clone, Default, Derivative)]
+#[derivative(Hash)]
 pub struct Wall {
     pub left: bool,
     pub right: bool,
+    #[derivative(Hash = "ignore")]
+    pub normal: Vec2,
+    #[derivative(Hash = "ignore")]
+    pub entity: Option<Entity>,
 }
 
-#[derive(Hash, Copy, Clone, Default)]
+#[derive(Copy, Clone, Default, Derivative)]
+#[derivative(Hash)]
 pub struct Floor {
     pub on: bool,
+    pub one_way: bool,
+    #[derivative(Hash = "ignore")]
+    pub normal: Vec2,
+    /// Slope of the floor relative to up, in radians.
+    #[derivative(Hash = "ignore")]
+    pub angle: f32,
+    #[derivative(Hash = "ignore")]
+    pub entity: Option<Entity>,
+    #[derivative(Hash = "ignore")]
+    pub friction: f32,
+    #[derivative(Hash = "ignore")]
+    pub restitution: f32,
+    /// Material of the floor entity, if it has one.
+    pub material: Option<AssetId<PhysicsMaterial>>,
+    /// Time spent off the floor, zero while on it.
+    pub time_since_grounded: Duration,
 }
 
-#[derive(Hash, Copy, Clone, Default)]
+#[derive(Copy, Clone, Default, Derivative)]
+#[derivative(Hash)]
 pub struct Ceiling {
     pub on: bool,
+    #[derivative(Hash = "ignore")]
+    pub normal: Vec2,
+    #[derivative(Hash = "ignore")]
+    pub entity: Option<Entity>,
+}
+
+/// Surface hit by a character collision, relative to its up vector.
+#[derive(Copy, Clone, PartialEq)]
+pub(crate) enum PhysicsCharacterContact {
+    Floor,
+    Wall,
+    Ceiling,
 }
 
 #[derive(Copy, Clone, Component, Derivative)]
@@ -27,15 +112,62 @@ pub struct PhysicsCharacterController {
     pub up: Vec2,
     #[derivative(Hash = "ignore")]
     pub right: Vec2,
+    /// Velocity along the right and up vectors, in pixels per rollback frame.
     #[derivative(Hash = "ignore")]
     pub velocity: Vec2,
+    /// Strength of the gravity relative to the global one, updated by gravity zones.
+    #[derivative(Hash = "ignore")]
+    pub gravity_scale: f32,
     //
     pub wall: Wall,
     pub floor: Floor,
     pub ceiling: Ceiling,
     //
+    pub options: PhysicsCharacterControllerOptions,
+    //
+    /// Kinematic body the character is standing on.
+    pub(crate) platform: Option<RigidBodyHandle>,
+    /// Velocity of the platform during the last move in world space, in the same units as the velocity.
     #[derivative(Hash = "ignore")]
-    pub(crate) rapier_controller: KinematicCharacterController,
+    pub platform_velocity: Vec2,
+    /// Ignores one-way colliders until the character no longer overlaps any of them.
+    pub(crate) dropping_through: bool,
+    /// Overlapped a gravity zone during the last frame, its up and right vectors are reset when it leaves.
+    pub(crate) in_gravity_zone: bool,
+}
+
+impl PhysicsCharacterLength {
+    fn to_rapier(self, scaler: &Scaler) -> CharacterLength {
+        match self {
+            Self::Absolute(length) => CharacterLength::Absolute(scaler.pixels_to_meters(length)),
+            Self::Relative(length) => CharacterLength::Relative(length),
+        }
+    }
+}
+
+impl Default for PhysicsCharacterAutostep {
+    fn default() -> Self {
+        Self {
+            max_height: PhysicsCharacterLength::Relative(0.25),
+            min_width: PhysicsCharacterLength::Relative(0.5),
+            include_dynamic_bodies: true,
+        }
+    }
+}
+
+impl Default for PhysicsCharacterControllerOptions {
+    fn default() -> Self {
+        Self {
+            offset: PhysicsCharacterLength::Relative(0.01),
+            slide: true,
+            autostep: None,
+            max_slope_climb_angle: FRAC_PI_4,
+            min_slope_slide_angle: FRAC_PI_4,
+            snap_to_ground: Some(PhysicsCharacterLength::Relative(0.2)),
+            normal_nudge_factor: 1.0e-4,
+            push_mass: None,
+        }
+    }
 }
 
 impl Default for PhysicsCharacterController {
@@ -44,17 +176,27 @@ impl Default for PhysicsCharacterController {
             up: Vec2::Y,
             right: Vec2::X,
             velocity: default(),
+            gravity_scale: 1.0,
             //
             wall: default(),
             floor: default(),
             ceiling: default(),
             //
-            rapier_controller: KinematicCharacterController { slide: true, autostep: None, ..default() },
+            options: default(),
+            //
+            platform: None,
+            platform_velocity: default(),
+            dropping_through: false,
+            in_gravity_zone: false,
         }
     }
 }
 
 impl PhysicsCharacterController {
+    pub fn from_options(options: PhysicsCharacterControllerOptions) -> Self {
+        Self { options, ..default() }
+    }
+
     pub fn is_on_wall(&self) -> bool {
         self.wall.left || self.wall.right
     }
@@ -67,33 +209,146 @@ impl PhysicsCharacterController {
         self.ceiling.on
     }
 
-    pub(crate) fn update_with_movement(&mut self, movement: EffectiveCharacterMovement, collisions: Vec<CharacterCollision>) {
-        self.wall.left = false;
-        self.wall.right = false;
+    pub fn is_on_one_way_floor(&self) -> bool {
+        self.floor.on && self.floor.one_way
+    }
+
+    pub fn is_dropping_through(&self) -> bool {
+        self.dropping_through
+    }
+
+    pub fn is_in_gravity_zone(&self) -> bool {
+        self.in_gravity_zone
+    }
+
+    /// Falls through the one-way colliders the character is standing on.
+    pub fn drop_through(&mut self) {
+        self.dropping_through = true;
+    }
+
+    /// Returns the velocity in world space.
+    pub fn world_velocity(&self) -> Vec2 {
+        self.right * self.velocity.x + self.up * self.velocity.y
+    }
+
+    pub(crate) fn rapier_controller(&self, scaler: &Scaler) -> KinematicCharacterController {
+        KinematicCharacterController {
+            up: UnitVector::try_new(self.up.to_physics(), Real::EPSILON).unwrap_or(Vector::y_axis()),
+            offset: self.options.offset.to_rapier(scaler),
+            slide: self.options.slide,
+            autostep: self
+                .options
+                .autostep
+                .map(|autostep| CharacterAutostep {
+                    max_height: autostep.max_height.to_rapier(scaler),
+                    min_width: autostep.min_width.to_rapier(scaler),
+                    include_dynamic_bodies: autostep.include_dynamic_bodies,
+                }),
+            max_slope_climb_angle: self.options.max_slope_climb_angle,
+            min_slope_slide_angle: self.options.min_slope_slide_angle,
+            snap_to_ground: self
+                .options
+                .snap_to_ground
+                .map(|snap_to_ground| snap_to_ground.to_rapier(scaler)),
+            normal_nudge_factor: self.options.normal_nudge_factor,
+        }
+    }
+
+    /// Classifies a collision by its normal, slopes steeper than the climb angle are walls.
+    pub(crate) fn contact(&self, collision: &CharacterCollision) -> Option<(PhysicsCharacterContact, Vec2)> {
+        match collision.hit.status {
+            ShapeCastStatus::Failed | ShapeCastStatus::Converged | ShapeCastStatus::OutOfIterations => {
+                let normal = collision.hit.normal1.to_bevy();
+
+                if self.up.dot(normal) < 0.0 {
+                    Some((PhysicsCharacterContact::Ceiling, normal))
+                } else if abs(normal.angle_to(self.up)) > self.options.max_slope_climb_angle {
+                    Some((PhysicsCharacterContact::Wall, normal))
+                } else {
+                    Some((PhysicsCharacterContact::Floor, normal))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn is_floor_collision(&self, collision: &CharacterCollision) -> bool {
+        matches!(self.contact(collision), Some((PhysicsCharacterContact::Floor, _)))
+    }
+
+    /// Keeps track of the platform under the character.
+    /// Leaving a platform adds its velocity to the character, so jumping off a moving platform carries its motion.
+    pub(crate) fn update_with_platform(&mut self, platform: Option<RigidBodyHandle>, platform_velocity: Vec2) {
+        if self.platform.is_some() && platform.is_none() {
+            self.velocity += Vec2::new(
+                platform_velocity.dot(self.right),
+                platform_velocity.dot(self.up),
+            );
+        }
+        self.platform = platform;
+        self.platform_velocity = match platform {
+            Some(_) => platform_velocity,
+            None => Vec2::ZERO,
+        };
+    }
+
+    pub(crate) fn update_with_one_way(&mut self, is_on_one_way_floor: bool, is_overlapping_one_way: bool) {
+        self.floor.one_way = is_on_one_way_floor;
+        self.dropping_through &= is_overlapping_one_way;
+    }
+
+    /// Updates the contact flags after a move, walls and ceilings hit by earlier steps of the frame are kept when merging.
+    pub(crate) fn update_with_movement(&mut self, physics: &Physics, movement: EffectiveCharacterMovement, collisions: Vec<CharacterCollision>, merge: bool) {
+        let mut floor_collision = None;
+
+        if !merge {
+            self.wall = default();
+            self.ceiling = default();
+        }
         self.floor.on = movement.grounded;
-        self.ceiling.on = false;
 
         for collision in collisions.iter() {
-            match collision.hit.status {
-                rapier2d::parry::query::ShapeCastStatus::Failed
-                | rapier2d::parry::query::ShapeCastStatus::Converged
-                | rapier2d::parry::query::ShapeCastStatus::OutOfIterations => {
-                    let normal = collision.hit.normal1.to_bevy();
-                    let up_angle = normal.angle_to(self.up);
-
-                    if self.up.dot(normal) < 0.0 {
-                        self.ceiling.on = true;
-                    } else if abs(up_angle) > self.rapier_controller.max_slope_climb_angle {
-                        self.wall.left = self.right.dot(normal) > 0.0;
-                        self.wall.right = !self.wall.left;
-                    }
+            match self.contact(collision) {
+                Some((PhysicsCharacterContact::Ceiling, normal)) => {
+                    self.ceiling.on = true;
+                    self.ceiling.normal = normal;
+                    self.ceiling.entity = physics.collider_entity(collision.handle);
+                }
+                Some((PhysicsCharacterContact::Wall, normal)) => {
+                    self.wall.left = self.right.dot(normal) > 0.0;
+                    self.wall.right = !self.wall.left;
+                    self.wall.normal = normal;
+                    self.wall.entity = physics.collider_entity(collision.handle);
+                }
+                Some((PhysicsCharacterContact::Floor, normal)) if floor_collision.is_none() => {
+                    floor_collision = Some((normal, collision.handle));
                 }
                 _ => (),
             }
         }
-        // println!(
-        //     "left: {} right: {}, floor: {}, ceiling: {}",
-        //     self.wall.left, self.wall.right, self.floor.on, self.ceiling.on
-        // );
+
+        // Grounded without a floor collision means the character did not move away from the last floor.
+        match (self.floor.on, floor_collision) {
+            (true, Some((normal, collider_handle))) => {
+                let collider = physics.colliders.get(collider_handle);
+
+                self.floor.normal = normal;
+                self.floor.angle = abs(normal.angle_to(self.up));
+                self.floor.entity = physics.collider_entity(collider_handle);
+                self.floor.friction = collider.map_or(0.0, |collider| collider.friction());
+                self.floor.restitution = collider.map_or(0.0, |collider| collider.restitution());
+            }
+            (true, None) => (),
+            (false, _) => {
+                self.floor = Floor {
+                    time_since_grounded: self.floor.time_since_grounded,
+                    ..default()
+                };
+            }
+        }
+        self.floor.time_since_grounded = match self.floor.on {
+            true => Duration::ZERO,
+            false => self.floor.time_since_grounded + Duration::from_secs_f32(physics.integration_parameters.dt),
+        };
     }
 }
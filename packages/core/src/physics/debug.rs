@@ -0,0 +1,276 @@
+use bevy::prelude::*;
+use rapier2d::math::Real;
+use rapier2d::parry::shape::TypedShape;
+use rapier2d::prelude::*;
+
+use crate::physics::controller::PhysicsCharacterController;
+use crate::physics::{Physics, Scaler};
+use crate::utilities::maths::*;
+
+/// Length in pixels of the arrows drawn for character controller flags.
+const CONTROLLER_ARROW_LENGTH: f32 = 16.0;
+/// Seconds of motion drawn by the velocity arrows.
+const VELOCITY_ARROW_SCALE: f32 = 0.1;
+/// Length in pixels of the contact normals.
+const CONTACT_NORMAL_LENGTH: f32 = 8.0;
+
+/// Settings of the physics debug renderer, toggled with the hotkey.
+#[derive(Clone, Resource)]
+pub struct PhysicsDebug {
+    pub enabled: bool,
+    pub hotkey: KeyCode,
+    //
+    pub shapes: bool,
+    pub velocities: bool,
+    pub contacts: bool,
+    pub aabbs: bool,
+    pub sensors: bool,
+    pub controllers: bool,
+    //
+    /// Colour of the colliders belonging to each layer, the first matching layer is used.
+    pub layer_colors: Vec<(Group, Color)>,
+    pub shape_color: Color,
+    pub sensor_color: Color,
+    pub velocity_color: Color,
+    pub contact_color: Color,
+    pub aabb_color: Color,
+    pub controller_color: Color,
+}
+
+impl PhysicsDebug {
+    fn collider_color(&self, collider: &Collider) -> Color {
+        if collider.is_sensor() {
+            return self.sensor_color;
+        }
+        self.layer_colors
+            .iter()
+            .find(|(group, _)| {
+                collider
+                    .collision_groups()
+                    .memberships
+                    .intersects(*group)
+            })
+            .map_or(self.shape_color, |(_, color)| *color)
+    }
+}
+
+impl Default for PhysicsDebug {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            hotkey: KeyCode::F1,
+            //
+            shapes: true,
+            velocities: false,
+            contacts: false,
+            aabbs: false,
+            sensors: true,
+            controllers: false,
+            //
+            layer_colors: vec![],
+            shape_color: Color::linear_rgba(0.0, 1.0, 0.0, 0.2),
+            sensor_color: Color::linear_rgba(1.0, 1.0, 0.0, 0.2),
+            velocity_color: Color::linear_rgba(0.0, 0.5, 1.0, 0.8),
+            contact_color: Color::linear_rgba(1.0, 0.0, 0.0, 0.8),
+            aabb_color: Color::linear_rgba(1.0, 1.0, 1.0, 0.1),
+            controller_color: Color::linear_rgba(1.0, 0.5, 0.0, 0.8),
+        }
+    }
+}
+
+pub(crate) fn physics_debug_toggle_system(keys: Res<ButtonInput<KeyCode>>, mut debug: ResMut<PhysicsDebug>) {
+    if keys.just_pressed(debug.hotkey) {
+        debug.enabled = !debug.enabled;
+    }
+}
+
+pub(crate) fn physics_debug_system(
+    query: Query<(&Transform, &PhysicsCharacterController)>,
+    mut gizmos: Gizmos,
+    //
+    debug: Res<PhysicsDebug>,
+    scaler: Res<Scaler>,
+    physics: Res<Physics>,
+) {
+    if !debug.enabled {
+        return;
+    }
+
+    let to_pixels = |point: Point<Real>| scaler.meters_to_pixels(point.coords.to_bevy());
+
+    for (_, collider) in physics.colliders.iter() {
+        let is_visible = match collider.is_sensor() {
+            true => debug.sensors,
+            false => debug.shapes,
+        };
+
+        if is_visible {
+            draw_shape(
+                &mut gizmos,
+                &scaler,
+                collider.shape(),
+                collider.position(),
+                debug.collider_color(collider),
+            );
+        }
+        if debug.aabbs {
+            let aabb = collider.compute_aabb();
+
+            gizmos.rect_2d(
+                to_pixels(aabb.center()),
+                scaler.meters_to_pixels(aabb.extents().to_bevy()),
+                debug.aabb_color,
+            );
+        }
+    }
+    if debug.velocities {
+        for (_, body) in physics.bodies.iter() {
+            let start = scaler.meters_to_pixels(body.translation().to_bevy());
+            let velocity = scaler.meters_to_pixels(body.linvel().to_bevy());
+
+            if velocity != Vec2::ZERO {
+                gizmos.arrow_2d(
+                    start,
+                    start + velocity * VELOCITY_ARROW_SCALE,
+                    debug.velocity_color,
+                );
+            }
+        }
+    }
+    if debug.contacts {
+        for contact_pair in physics.narrow_phase.contact_pairs() {
+            for manifold in contact_pair.manifolds.iter() {
+                let normal = manifold.data.normal.to_bevy();
+
+                for contact in manifold.data.solver_contacts.iter() {
+                    let point = to_pixels(contact.point);
+
+                    gizmos.circle_2d(point, 1.0, debug.contact_color);
+                    gizmos.line_2d(
+                        point,
+                        point + normal * CONTACT_NORMAL_LENGTH,
+                        debug.contact_color,
+                    );
+                }
+            }
+        }
+    }
+    if debug.controllers {
+        for (transform, controller) in query.iter() {
+            let center = transform.translation.truncate();
+            let mut arrow = |direction: Vec2| {
+                gizmos.arrow_2d(
+                    center,
+                    center + direction * CONTROLLER_ARROW_LENGTH,
+                    debug.controller_color,
+                );
+            };
+
+            if controller.floor.on {
+                arrow(-controller.up);
+            }
+            if controller.ceiling.on {
+                arrow(controller.up);
+            }
+            if controller.wall.left {
+                arrow(-controller.right);
+            }
+            if controller.wall.right {
+                arrow(controller.right);
+            }
+            if controller.floor.on {
+                gizmos.line_2d(
+                    center,
+                    center + controller.floor.normal * CONTROLLER_ARROW_LENGTH,
+                    debug.contact_color,
+                );
+            }
+        }
+    }
+}
+
+fn draw_shape(gizmos: &mut Gizmos, scaler: &Scaler, shape: &dyn Shape, position: &Isometry<Real>, color: Color) {
+    let to_pixels = |point: &Point<Real>| scaler.meters_to_pixels((position * point).coords.to_bevy());
+    let isometry = |translation: Vec2, rotation: f32| Isometry2d {
+        rotation: Rot2::radians(rotation),
+        translation,
+    };
+
+    match shape.as_typed_shape() {
+        TypedShape::Ball(ball) => {
+            gizmos.circle_2d(
+                to_pixels(&Point::origin()),
+                scaler.meters_to_pixels(ball.radius),
+                color,
+            );
+        }
+        TypedShape::Cuboid(cuboid) => {
+            gizmos.rect_2d(
+                isometry(
+                    to_pixels(&Point::origin()),
+                    position.rotation.angle(),
+                ),
+                scaler.meters_to_pixels(cuboid.half_extents.to_bevy()) * 2.0,
+                color,
+            );
+        }
+        TypedShape::Capsule(capsule) => {
+            let a = to_pixels(&capsule.segment.a);
+            let b = to_pixels(&capsule.segment.b);
+
+            gizmos.primitive_2d(
+                &Capsule2d::new(
+                    scaler.meters_to_pixels(capsule.radius),
+                    a.distance(b),
+                ),
+                isometry((a + b) / 2.0, Vec2::Y.angle_to((b - a).normalize_or(Vec2::Y))),
+                color,
+            );
+        }
+        TypedShape::Segment(segment) => {
+            gizmos.line_2d(to_pixels(&segment.a), to_pixels(&segment.b), color);
+        }
+        TypedShape::Triangle(triangle) => {
+            gizmos.linestrip_2d(
+                [triangle.a, triangle.b, triangle.c, triangle.a]
+                    .iter()
+                    .map(to_pixels),
+                color,
+            );
+        }
+        TypedShape::ConvexPolygon(polygon) => {
+            gizmos.linestrip_2d(
+                polygon
+                    .points()
+                    .iter()
+                    .chain(polygon.points().first())
+                    .map(to_pixels),
+                color,
+            );
+        }
+        TypedShape::Polyline(polyline) => {
+            for segment in polyline.segments() {
+                gizmos.line_2d(to_pixels(&segment.a), to_pixels(&segment.b), color);
+            }
+        }
+        TypedShape::HeightField(heightfield) => {
+            for segment in heightfield.segments() {
+                gizmos.line_2d(to_pixels(&segment.a), to_pixels(&segment.b), color);
+            }
+        }
+        TypedShape::Compound(compound) => {
+            for (shape_position, shape) in compound.shapes() {
+                draw_shape(gizmos, scaler, &**shape, &(position * shape_position), color);
+            }
+        }
+        _ => {
+            let aabb = shape.compute_aabb(position);
+
+            gizmos.rect_2d(
+                scaler.meters_to_pixels(aabb.center().coords.to_bevy()),
+                scaler.meters_to_pixels(aabb.extents().to_bevy()),
+                color,
+            );
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+use derivative::Derivative;
+
+use crate::event::events::RollbackEvent;
+
+/// Entity ids differ between peers once a rollback respawns them, they are left out of the checksums of events.
+#[derive(Copy, Clone, PartialEq, Derivative)]
+#[derivative(Hash)]
+pub enum PhysicsCollisionEvent {
+    Started {
+        #[derivative(Hash = "ignore")]
+        entity1: Entity,
+        #[derivative(Hash = "ignore")]
+        entity2: Entity,
+    },
+    Stopped {
+        #[derivative(Hash = "ignore")]
+        entity1: Entity,
+        #[derivative(Hash = "ignore")]
+        entity2: Entity,
+    },
+}
+
+#[derive(Copy, Clone, PartialEq, Derivative)]
+#[derivative(Hash)]
+pub enum PhysicsSensorEvent {
+    Entered {
+        #[derivative(Hash = "ignore")]
+        sensor: Entity,
+        #[derivative(Hash = "ignore")]
+        entity: Entity,
+    },
+    Exited {
+        #[derivative(Hash = "ignore")]
+        sensor: Entity,
+        #[derivative(Hash = "ignore")]
+        entity: Entity,
+    },
+}
+
+#[derive(Copy, Clone, Derivative)]
+#[derivative(Hash)]
+pub struct PhysicsContactForceEvent {
+    #[derivative(Hash = "ignore")]
+    pub entity1: Entity,
+    #[derivative(Hash = "ignore")]
+    pub entity2: Entity,
+    #[derivative(Hash = "ignore")]
+    pub total_force: Vec2,
+    #[derivative(Hash = "ignore")]
+    pub total_force_magnitude: f32,
+    #[derivative(Hash = "ignore")]
+    pub max_force_direction: Vec2,
+    #[derivative(Hash = "ignore")]
+    pub max_force_magnitude: f32,
+}
+
+impl PhysicsCollisionEvent {
+    pub fn entities(&self) -> (Entity, Entity) {
+        match *self {
+            Self::Started { entity1, entity2 } => (entity1, entity2),
+            Self::Stopped { entity1, entity2 } => (entity1, entity2),
+        }
+    }
+
+    pub fn involves(&self, entity: Entity) -> bool {
+        let (entity1, entity2) = self.entities();
+
+        entity1 == entity || entity2 == entity
+    }
+}
+
+impl PhysicsSensorEvent {
+    pub fn sensor(&self) -> Entity {
+        match *self {
+            Self::Entered { sensor, .. } => sensor,
+            Self::Exited { sensor, .. } => sensor,
+        }
+    }
+
+    pub fn entity(&self) -> Entity {
+        match *self {
+            Self::Entered { entity, .. } => entity,
+            Self::Exited { entity, .. } => entity,
+        }
+    }
+}
+
+impl PhysicsContactForceEvent {
+    pub fn involves(&self, entity: Entity) -> bool {
+        self.entity1 == entity || self.entity2 == entity
+    }
+}
+
+impl RollbackEvent for PhysicsCollisionEvent {}
+impl RollbackEvent for PhysicsSensorEvent {}
+impl RollbackEvent for PhysicsContactForceEvent {}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_ggrs::{GgrsTime, Rollback, RollbackOrdered};
+use derivative::Derivative;
+use rapier2d::prelude::*;
+
+use crate::physics::body::PhysicsBodyHandle;
+use crate::physics::collider::PhysicsColliderHandle;
+use crate::physics::controller::PhysicsCharacterController;
+use crate::physics::{Physics, Scaler};
+use crate::utilities::cmp::cmp_rollback;
+use crate::utilities::maths::*;
+
+/// Replaces the gravity of the dynamic bodies and character controllers overlapping the collider of this entity.
+/// Gravity is in pixels per second squared, overlapping zones are applied in rollback order and the last one wins.
+#[derive(Copy, Clone, Component, Derivative)]
+#[derivative(Hash)]
+pub struct PhysicsGravityZone {
+    #[derivative(Hash = "ignore")]
+    pub gravity: Vec2,
+}
+
+#[derive(Hash, Copy, Clone, PartialEq)]
+pub enum GravityFalloff {
+    /// Acceleration grows with the inverse square of the distance, it is the strength at the radius.
+    InverseSquare,
+    /// Acceleration decreases linearly from the strength at the center to zero at the radius.
+    Linear,
+}
+
+/// Attracts the dynamic bodies and character controllers within the radius towards the translation of this entity.
+/// Strength is in pixels per second squared, radius and minimum distance are in pixels.
+#[derive(Copy, Clone, Component, Derivative)]
+#[derivative(Hash)]
+pub struct GravityWell {
+    #[derivative(Hash = "ignore")]
+    pub strength: f32,
+    #[derivative(Hash = "ignore")]
+    pub radius: f32,
+    /// Distance under which the inverse square falloff stops growing.
+    #[derivative(Hash = "ignore")]
+    pub min_distance: f32,
+    pub falloff: GravityFalloff,
+}
+
+impl PhysicsGravityZone {
+    pub fn new(gravity: Vec2) -> Self {
+        Self { gravity }
+    }
+}
+
+impl GravityWell {
+    pub fn new(strength: f32, radius: f32, falloff: GravityFalloff) -> Self {
+        Self {
+            strength,
+            radius,
+            min_distance: 1.0,
+            falloff,
+        }
+    }
+
+    /// Returns the acceleration towards the well at the given offset from its center.
+    pub fn acceleration(&self, offset: Vec2) -> Vec2 {
+        let distance = offset.length();
+
+        if distance >= self.radius || distance <= 0.0 {
+            return Vec2::ZERO;
+        }
+
+        let strength = match self.falloff {
+            GravityFalloff::InverseSquare => self.strength * (self.radius / distance.max(self.min_distance)).powi(2),
+            GravityFalloff::Linear => self.strength * (1.0 - distance / self.radius),
+        };
+
+        -offset / distance * strength
+    }
+}
+
+/// Returns the up and right vectors of a character under the given gravity.
+/// Right keeps pointing towards positive x when the gravity is inverted, so inputs stay aligned with the screen.
+pub fn gravity_basis(gravity: Vec2) -> (Vec2, Vec2) {
+    let up = (-gravity).normalize_or(Vec2::Y);
+    let right = Vec2::new(up.y, -up.x);
+
+    match up.y < 0.0 {
+        true => (up, -right),
+        false => (up, right),
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn physics_gravity_system(
+    zone_query: Query<(
+        &Rollback,
+        &PhysicsGravityZone,
+        &PhysicsBodyHandle,
+        &PhysicsColliderHandle,
+    )>,
+    mut controller_query: Query<(
+        &Rollback,
+        &PhysicsBodyHandle,
+        &mut PhysicsCharacterController,
+    )>,
+    //
+    time: Res<Time<GgrsTime>>,
+    order: Res<RollbackOrdered>,
+    scaler: Res<Scaler>,
+    mut physics: ResMut<Physics>,
+) {
+    let mut zone_query = zone_query.iter().collect::<Vec<_>>();
+    let mut controller_query = controller_query.iter_mut().collect::<Vec<_>>();
+    let mut gravities = HashMap::new();
+
+    zone_query.sort_by(|(rollback_a, ..), (rollback_b, ..)| cmp_rollback(&order, rollback_a, rollback_b));
+    controller_query.sort_by(|(rollback_a, ..), (rollback_b, ..)| cmp_rollback(&order, rollback_a, rollback_b));
+
+    for (_, zone, body_handle, collider_handle) in zone_query {
+        let Some(collider) = physics.colliders.get(collider_handle.handle()) else {
+            continue;
+        };
+        let gravity = scaler.pixels_to_meters(zone.gravity).to_physics();
+
+        physics.query_pipeline.intersections_with_shape(
+            &physics.bodies,
+            &physics.colliders,
+            collider.position(),
+            collider.shape(),
+            QueryFilter::default()
+                .exclude_sensors()
+                .exclude_rigid_body(body_handle.handle()),
+            |handle| {
+                if let Some(body_handle) = physics
+                    .colliders
+                    .get(handle)
+                    .and_then(|collider| collider.parent())
+                {
+                    gravities.insert(body_handle, gravity);
+                }
+                true
+            },
+        );
+    }
+
+    let dt = time.delta_secs();
+    let global_gravity = physics.gravity;
+
+    // Rapier only supports a global gravity, the difference is applied as a velocity change once per frame.
+    for (body_handle, gravity) in gravities.iter() {
+        if let Some(body) = physics.bodies.get_mut(*body_handle) {
+            if body.is_dynamic() {
+                let linvel = *body.linvel() + (gravity - global_gravity) * body.gravity_scale() * dt;
+
+                body.set_linvel(linvel, true);
+            }
+        }
+    }
+    // Only controllers inside a zone or leaving one are realigned, the up vector of the others is left to gameplay code.
+    for (_, body_handle, mut character_controller) in controller_query {
+        let gravity = match gravities.get(&body_handle.handle()) {
+            Some(gravity) => *gravity,
+            None if character_controller.in_gravity_zone => global_gravity,
+            None => continue,
+        };
+        let (up, right) = gravity_basis(gravity.to_bevy());
+
+        character_controller.up = up;
+        character_controller.right = right;
+        character_controller.gravity_scale = match global_gravity.norm() > 0.0 {
+            true => gravity.norm() / global_gravity.norm(),
+            false => 1.0,
+        };
+        character_controller.in_gravity_zone = gravities.contains_key(&body_handle.handle());
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn physics_gravity_well_system(
+    well_query: Query<(&Rollback, &GravityWell, &Transform)>,
+    mut controller_query: Query<(
+        &Rollback,
+        &PhysicsBodyHandle,
+        &mut PhysicsCharacterController,
+    )>,
+    //
+    time: Res<Time<GgrsTime>>,
+    order: Res<RollbackOrdered>,
+    scaler: Res<Scaler>,
+    mut physics: ResMut<Physics>,
+) {
+    let mut well_query = well_query.iter().collect::<Vec<_>>();
+    let mut controller_query = controller_query.iter_mut().collect::<Vec<_>>();
+
+    well_query.sort_by(|(rollback_a, ..), (rollback_b, ..)| cmp_rollback(&order, rollback_a, rollback_b));
+    controller_query.sort_by(|(rollback_a, ..), (rollback_b, ..)| cmp_rollback(&order, rollback_a, rollback_b));
+
+    let dt = time.delta_secs();
+
+    // Mutable iteration marks every body as modified, only the bodies in range of a well are fetched mutably.
+    let body_handles = match well_query.is_empty() {
+        true => vec![],
+        false => physics
+            .bodies
+            .iter()
+            .filter(|(_, body)| body.is_dynamic())
+            .map(|(body_handle, _)| body_handle)
+            .collect::<Vec<_>>(),
+    };
+
+    // Wells are summed in rollback order and bodies are visited in arena order.
+    for (_, well, transform) in well_query.iter() {
+        let center = transform.translation.truncate();
+
+        for body_handle in body_handles.iter() {
+            let Some(body) = physics.bodies.get(*body_handle) else {
+                continue;
+            };
+            let offset = scaler.meters_to_pixels(body.translation().to_bevy()) - center;
+            let acceleration = well.acceleration(offset);
+
+            if acceleration != Vec2::ZERO {
+                if let Some(body) = physics.bodies.get_mut(*body_handle) {
+                    let linvel = *body.linvel() + scaler.pixels_to_meters(acceleration).to_physics() * dt;
+
+                    body.set_linvel(linvel, true);
+                }
+            }
+        }
+    }
+    // Controller velocities are in pixels per frame along their right and up vectors.
+    for (_, body_handle, mut character_controller) in controller_query {
+        let Some(body) = physics.bodies.get(body_handle.handle()) else {
+            continue;
+        };
+        let translation = scaler.meters_to_pixels(body.translation().to_bevy());
+        let acceleration = well_query
+            .iter()
+            .map(|(_, well, transform)| well.acceleration(translation - transform.translation.truncate()))
+            .fold(Vec2::ZERO, |total, acceleration| total + acceleration)
+            * dt
+            * dt;
+
+        let (right, up) = (character_controller.right, character_controller.up);
+
+        character_controller.velocity += Vec2::new(acceleration.dot(right), acceleration.dot(up));
+    }
+}
@@ -0,0 +1,195 @@
+use bevy::ecs::component::HookContext;
+use bevy::ecs::entity::{EntityMapper, MapEntities};
+use bevy::ecs::world::DeferredWorld;
+use bevy::prelude::*;
+use derivative::Derivative;
+use rapier2d::prelude::*;
+
+use crate::physics::PhysicsRemovals;
+use crate::utilities::maths::*;
+use crate::Scaler;
+
+#[derive(Copy, Clone, Derivative)]
+#[derivative(Hash)]
+pub enum PhysicsJointMotor {
+    /// Spring-like motor driving the joint towards a target position.
+    Position {
+        #[derivative(Hash = "ignore")]
+        target: f32,
+        #[derivative(Hash = "ignore")]
+        stiffness: f32,
+        #[derivative(Hash = "ignore")]
+        damping: f32,
+        #[derivative(Hash = "ignore")]
+        max_force: f32,
+    },
+    /// Motor driving the joint towards a target velocity.
+    Velocity {
+        #[derivative(Hash = "ignore")]
+        target: f32,
+        #[derivative(Hash = "ignore")]
+        factor: f32,
+        #[derivative(Hash = "ignore")]
+        max_force: f32,
+    },
+}
+
+#[derive(Copy, Clone, Derivative)]
+#[derivative(Hash)]
+pub enum PhysicsJointKind {
+    Fixed,
+    /// Rotation around the anchors, limits and motor are in radians.
+    Revolute {
+        #[derivative(Hash = "ignore")]
+        limits: Option<[f32; 2]>,
+        motor: Option<PhysicsJointMotor>,
+    },
+    /// Translation along the axis, limits and motor are in pixels.
+    Prismatic {
+        #[derivative(Hash = "ignore")]
+        axis: Vec2,
+        #[derivative(Hash = "ignore")]
+        limits: Option<[f32; 2]>,
+        motor: Option<PhysicsJointMotor>,
+    },
+    /// Keeps the anchors at most at the given length.
+    Rope {
+        #[derivative(Hash = "ignore")]
+        length: f32,
+    },
+    /// Pulls the anchors towards the given rest length.
+    Spring {
+        #[derivative(Hash = "ignore")]
+        rest_length: f32,
+        #[derivative(Hash = "ignore")]
+        stiffness: f32,
+        #[derivative(Hash = "ignore")]
+        damping: f32,
+    },
+}
+
+/// Joint between the body of this entity and the body of the target rollback entity.
+#[derive(Copy, Clone, Component, Derivative)]
+#[derivative(Hash)]
+pub struct PhysicsJoint {
+    pub kind: PhysicsJointKind,
+    /// Entity ids differ between peers once a rollback respawns the target, it is left out of the checksum.
+    #[derivative(Hash = "ignore")]
+    pub target: Entity,
+    #[derivative(Hash = "ignore")]
+    pub anchor: Vec2,
+    #[derivative(Hash = "ignore")]
+    pub target_anchor: Vec2,
+    pub contacts_enabled: bool,
+}
+
+#[derive(Hash, Copy, Clone, Component)]
+#[component(on_remove = on_remove_joint_handle)]
+pub struct PhysicsJointHandle(pub(crate) ImpulseJointHandle);
+
+impl PhysicsJoint {
+    pub fn new(kind: PhysicsJointKind, target: Entity) -> Self {
+        Self {
+            kind,
+            target,
+            anchor: default(),
+            target_anchor: default(),
+            contacts_enabled: true,
+        }
+    }
+
+    pub fn with_anchors(mut self, anchor: Vec2, target_anchor: Vec2) -> Self {
+        self.anchor = anchor;
+        self.target_anchor = target_anchor;
+        self
+    }
+
+    pub fn with_contacts_enabled(mut self, contacts_enabled: bool) -> Self {
+        self.contacts_enabled = contacts_enabled;
+        self
+    }
+
+    pub(crate) fn build(&self, scaler: &Scaler) -> GenericJoint {
+        let mut joint: GenericJoint = match self.kind {
+            PhysicsJointKind::Fixed => FixedJointBuilder::new().into(),
+            PhysicsJointKind::Revolute { .. } => RevoluteJointBuilder::new().into(),
+            PhysicsJointKind::Prismatic { axis, .. } => PrismaticJointBuilder::new(UnitVector::new_normalize(axis.to_physics())).into(),
+            PhysicsJointKind::Rope { length } => RopeJointBuilder::new(scaler.pixels_to_meters(length)).into(),
+            PhysicsJointKind::Spring { rest_length, stiffness, damping } => SpringJointBuilder::new(
+                scaler.pixels_to_meters(rest_length),
+                stiffness,
+                damping,
+            )
+            .into(),
+        };
+
+        joint
+            .set_local_anchor1(Point::from(
+                scaler
+                    .pixels_to_meters(self.anchor)
+                    .to_physics(),
+            ))
+            .set_local_anchor2(Point::from(
+                scaler
+                    .pixels_to_meters(self.target_anchor)
+                    .to_physics(),
+            ))
+            .set_contacts_enabled(self.contacts_enabled);
+
+        match self.kind {
+            PhysicsJointKind::Revolute { limits, motor } => Self::apply_axis(&mut joint, JointAxis::AngX, 1.0, limits, motor),
+            PhysicsJointKind::Prismatic { limits, motor, .. } => Self::apply_axis(
+                &mut joint,
+                JointAxis::LinX,
+                scaler.pixels_to_meters(1.0),
+                limits,
+                motor,
+            ),
+            _ => (),
+        }
+        joint
+    }
+
+    fn apply_axis(joint: &mut GenericJoint, axis: JointAxis, scale: f32, limits: Option<[f32; 2]>, motor: Option<PhysicsJointMotor>) {
+        if let Some([min, max]) = limits {
+            joint.set_limits(axis, [min * scale, max * scale]);
+        }
+        match motor {
+            None => (),
+            Some(PhysicsJointMotor::Position { target, stiffness, damping, max_force }) => {
+                joint
+                    .set_motor_position(axis, target * scale, stiffness, damping)
+                    .set_motor_max_force(axis, max_force * scale);
+            }
+            Some(PhysicsJointMotor::Velocity { target, factor, max_force }) => {
+                joint
+                    .set_motor_velocity(axis, target * scale, factor)
+                    .set_motor_max_force(axis, max_force * scale);
+            }
+        }
+    }
+}
+
+impl MapEntities for PhysicsJoint {
+    /// Follows the target when a rollback respawns it.
+    fn map_entities<E: EntityMapper>(&mut self, entity_mapper: &mut E) {
+        self.target = entity_mapper.get_mapped(self.target);
+    }
+}
+
+impl PhysicsJointHandle {
+    #[inline(always)]
+    pub fn handle(&self) -> ImpulseJointHandle {
+        self.0
+    }
+}
+
+fn on_remove_joint_handle(mut world: DeferredWorld, HookContext { entity, .. }: HookContext) {
+    let Some(joint_handle) = world.get::<PhysicsJointHandle>(entity).copied() else {
+        return;
+    };
+
+    if let Some(mut removals) = world.get_resource_mut::<PhysicsRemovals>() {
+        removals.joints.push((entity, joint_handle.handle()));
+    }
+}
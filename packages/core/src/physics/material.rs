@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+use bevy::reflect::TypePath;
+use derivative::Derivative;
+use rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::physics::controller::PhysicsCharacterController;
+
+/// Rule combining the coefficients of two colliders in contact.
+/// When the colliders use different rules, the one declared last wins.
+#[derive(Hash, Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum PhysicsCombineRule {
+    #[default]
+    Average,
+    Min,
+    Multiply,
+    Max,
+}
+
+/// Named surface properties shared by colliders, defined in the asset collection.
+#[derive(Asset, TypePath, Copy, Clone, Debug, PartialEq, Serialize, Deserialize, Derivative)]
+#[derivative(Hash)]
+#[serde(default)]
+pub struct PhysicsMaterial {
+    #[derivative(Hash = "ignore")]
+    pub friction: f32,
+    #[derivative(Hash = "ignore")]
+    pub restitution: f32,
+    pub friction_combine_rule: PhysicsCombineRule,
+    pub restitution_combine_rule: PhysicsCombineRule,
+}
+
+/// Material of the main collider of this entity, it replaces the friction and restitution of its options.
+#[derive(Hash, Clone, Component)]
+pub struct PhysicsColliderMaterial(pub Handle<PhysicsMaterial>);
+
+impl PhysicsMaterial {
+    pub fn new(friction: f32, restitution: f32) -> Self {
+        Self {
+            friction,
+            restitution,
+            ..default()
+        }
+    }
+
+    pub(crate) fn apply(&self, collider: &mut Collider) {
+        collider.set_friction(self.friction);
+        collider.set_restitution(self.restitution);
+        collider.set_friction_combine_rule(self.friction_combine_rule.into());
+        collider.set_restitution_combine_rule(self.restitution_combine_rule.into());
+    }
+}
+
+impl Default for PhysicsMaterial {
+    fn default() -> Self {
+        Self {
+            friction: 1.0,
+            restitution: 0.1,
+            friction_combine_rule: default(),
+            restitution_combine_rule: default(),
+        }
+    }
+}
+
+impl From<PhysicsCombineRule> for CoefficientCombineRule {
+    fn from(rule: PhysicsCombineRule) -> Self {
+        match rule {
+            PhysicsCombineRule::Average => CoefficientCombineRule::Average,
+            PhysicsCombineRule::Min => CoefficientCombineRule::Min,
+            PhysicsCombineRule::Multiply => CoefficientCombineRule::Multiply,
+            PhysicsCombineRule::Max => CoefficientCombineRule::Max,
+        }
+    }
+}
+
+pub(crate) fn physics_controller_material_system(
+    mut query: Query<&mut PhysicsCharacterController>,
+    materials: Query<&PhysicsColliderMaterial>,
+) {
+    // Each controller only reads the material of its own floor, the iteration order does not matter.
+    for mut character_controller in query.iter_mut() {
+        let material = character_controller
+            .floor
+            .entity
+            .and_then(|e| materials.get(e).ok())
+            .map(|material| material.0.id());
+
+        if character_controller.floor.material != material {
+            character_controller.floor.material = material;
+        }
+    }
+}
@@ -1,28 +1,48 @@
 pub mod body;
 pub mod collider;
 pub mod controller;
+pub mod debug;
+pub mod events;
+pub mod gravity;
+pub mod joint;
+pub mod material;
+pub mod query;
+pub mod snapshot;
 
 use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
+use std::time::Duration;
 
+use bevy::ecs::entity::{EntityMapper, MapEntities};
 use bevy::ecs::schedule::ScheduleConfigs;
 use bevy::ecs::system::ScheduleSystem;
 use bevy::prelude::*;
-use bevy_ggrs::{Rollback, RollbackOrdered};
+use bevy_ggrs::{GgrsTime, Rollback, RollbackOrdered};
 use rapier2d::math::Real;
 use rapier2d::{crossbeam, prelude::*};
 
-use crate::body::{PhysicsBodyOptions, PhysicsBodyVelocity};
-use crate::collider::PhysicsColliderOptions;
+use crate::body::{PhysicsBodyOptions, PhysicsBodyVelocity, PhysicsForce, PhysicsImpulse, PhysicsTeleport};
+use crate::collider::{PhysicsChildColliderHandles, PhysicsChildColliders, PhysicsColliderOptions};
+use crate::event::events::RollbackEvents;
 use crate::physics::body::PhysicsBody;
 use crate::physics::body::PhysicsBodyHandle;
 use crate::physics::collider::PhysicsCollider;
 use crate::physics::collider::PhysicsColliderHandle;
 use crate::physics::controller::PhysicsCharacterController;
+use crate::physics::debug::{physics_debug_system, physics_debug_toggle_system};
+use crate::physics::events::{PhysicsCollisionEvent, PhysicsContactForceEvent, PhysicsSensorEvent};
+use crate::physics::gravity::{physics_gravity_system, physics_gravity_well_system};
+use crate::physics::joint::{PhysicsJoint, PhysicsJointHandle};
+use crate::physics::material::{physics_controller_material_system, PhysicsColliderMaterial, PhysicsMaterial};
 use crate::utilities::cmp::cmp_rollback;
 use crate::utilities::hash::f32_hasher;
 use crate::utilities::maths::*;
 
+/// Distance in pixels a character can sink into a one-way collider and still land on it.
+const ONE_WAY_TOLERANCE: f32 = 1.0;
+/// Slack allowed when comparing the accumulated frame time with the timestep, absorbs the rounding of both durations.
+const TIMESTEP_TOLERANCE: Duration = Duration::from_micros(10);
+
 #[derive(Copy, Clone, Resource)]
 pub struct Scaler {
     pub scale: f32,
@@ -43,8 +63,52 @@ pub struct Physics {
     pub multibody_joints: MultibodyJointSet,
     pub integration_parameters: IntegrationParameters,
     //
+    /// Duration simulated by each physics tick, independent of the rollback frame rate.
+    /// It must not exceed a rollback frame, controller movement is only applied on frames with a tick.
+    /// A rollback frame should also be a whole number of ticks, or characters move unevenly between frames.
+    pub timestep: Duration,
+    /// Number of steps each tick is split into, the step length is set from the timestep before stepping.
+    pub substeps: usize,
+    /// Frame time not yet simulated.
+    pub accumulator: Duration,
+    //
     pub body_handles_by_entity: HashMap<Entity, RigidBodyHandle>,
     pub collider_handles_by_entity: HashMap<Entity, ColliderHandle>,
+    pub entities_by_body_handle: HashMap<RigidBodyHandle, Entity>,
+    /// Includes child colliders, which are not in `collider_handles_by_entity`.
+    pub entities_by_collider_handle: HashMap<ColliderHandle, Entity>,
+    pub entities_by_joint_handle: HashMap<ImpulseJointHandle, Entity>,
+    //
+    pub one_way_colliders: HashSet<ColliderHandle>,
+}
+
+/// Scratch state reused by every [`Physics::step`].
+/// It is not rolled back, the pipeline only keeps buffers and the channels are drained after each step.
+#[derive(Resource)]
+pub struct PhysicsWorkspace {
+    pipeline: PhysicsPipeline,
+    event_collector: ChannelEventCollector,
+    collision_event_receiver: crossbeam::channel::Receiver<CollisionEvent>,
+    contact_force_event_receiver: crossbeam::channel::Receiver<ContactForceEvent>,
+}
+
+/// Handles of the entities despawned or stripped of their handle components, queued by component hooks.
+/// It is rolled back with [`Physics`], entities despawned after the physics systems leave their handles queued in the saved frame.
+#[derive(Default, Clone, Resource)]
+pub struct PhysicsRemovals {
+    pub(crate) bodies: Vec<(Entity, RigidBodyHandle)>,
+    pub(crate) colliders: Vec<(Entity, ColliderHandle)>,
+    pub(crate) joints: Vec<(Entity, ImpulseJointHandle)>,
+    pub(crate) forces: Vec<(Entity, RigidBodyHandle)>,
+}
+
+impl PhysicsRemovals {
+    fn clear(&mut self) {
+        self.bodies.clear();
+        self.colliders.clear();
+        self.joints.clear();
+        self.forces.clear();
+    }
 }
 
 impl Scaler {
@@ -67,11 +131,8 @@ impl Scaler {
 
 impl Physics {
     #[allow(clippy::let_unit_value)]
-    pub fn step(&mut self) {
-        let (collision_event_sender, _) = crossbeam::channel::unbounded();
-        let (contact_force_event_sender, _) = crossbeam::channel::unbounded();
-
-        PhysicsPipeline::new().step(
+    pub fn step(&mut self, workspace: &mut PhysicsWorkspace) -> (Vec<CollisionEvent>, Vec<ContactForceEvent>) {
+        workspace.pipeline.step(
             &self.gravity,
             &self.integration_parameters,
             &mut self.island_manager,
@@ -84,22 +145,122 @@ impl Physics {
             &mut self.ccd_solver,
             Some(&mut self.query_pipeline),
             &(),
-            &ChannelEventCollector::new(collision_event_sender, contact_force_event_sender),
+            &workspace.event_collector,
+        );
+
+        (
+            workspace
+                .collision_event_receiver
+                .try_iter()
+                .collect(),
+            workspace
+                .contact_force_event_receiver
+                .try_iter()
+                .collect(),
+        )
+    }
+
+    /// Returns the length of a single step.
+    pub fn substep_dt(&self) -> Real {
+        self.timestep.as_secs_f32() / self.substeps.max(1) as Real
+    }
+
+    /// Adds the frame time to the accumulator and returns the number of ticks to simulate.
+    pub fn accumulate(&mut self, delta: Duration) -> usize {
+        let mut ticks = 0;
+
+        debug_assert!(
+            delta.is_zero() || self.timestep <= delta + TIMESTEP_TOLERANCE,
+            "Physics timestep {:?} exceeds the frame time {:?}",
+            self.timestep,
+            delta
         );
+
+        self.accumulator += delta;
+        if self.timestep.is_zero() {
+            return 0;
+        }
+        while self.accumulator + TIMESTEP_TOLERANCE >= self.timestep {
+            self.accumulator = self.accumulator.saturating_sub(self.timestep);
+            ticks += 1;
+        }
+        ticks
     }
 
     //
 
-    pub fn insert_body(&mut self, body: RigidBody, collider: Collider) -> (RigidBodyHandle, ColliderHandle) {
+    pub fn body_entity(&self, body_handle: RigidBodyHandle) -> Option<Entity> {
+        self.entities_by_body_handle
+            .get(&body_handle)
+            .copied()
+    }
+
+    pub fn collider_entity(&self, collider_handle: ColliderHandle) -> Option<Entity> {
+        self.entities_by_collider_handle
+            .get(&collider_handle)
+            .copied()
+    }
+
+    pub fn joint_entity(&self, joint_handle: ImpulseJointHandle) -> Option<Entity> {
+        self.entities_by_joint_handle
+            .get(&joint_handle)
+            .copied()
+    }
+
+    pub fn entity_body(&self, e: Entity) -> Option<RigidBodyHandle> {
+        self.body_handles_by_entity.get(&e).copied()
+    }
+
+    pub fn entity_collider(&self, e: Entity) -> Option<ColliderHandle> {
+        self.collider_handles_by_entity.get(&e).copied()
+    }
+
+    //
+
+    /// Inserts the body of an entity along with its main collider.
+    pub fn insert_body(&mut self, e: Entity, body: RigidBody, collider: Collider) -> (RigidBodyHandle, ColliderHandle) {
         let body_handle = self.bodies.insert(body);
+        let collider_handle = self.insert_collider(e, collider, body_handle);
+
+        self.body_handles_by_entity.insert(e, body_handle);
+        self.entities_by_body_handle.insert(body_handle, e);
+        self.collider_handles_by_entity
+            .insert(e, collider_handle);
+        (body_handle, collider_handle)
+    }
+
+    /// Inserts a collider owned by an entity.
+    pub fn insert_collider(&mut self, e: Entity, collider: Collider, body_handle: RigidBodyHandle) -> ColliderHandle {
         let collider_handle = self
             .colliders
             .insert_with_parent(collider, body_handle, &mut self.bodies);
 
-        (body_handle, collider_handle)
+        self.entities_by_collider_handle
+            .insert(collider_handle, e);
+        collider_handle
+    }
+
+    /// Inserts a joint owned by an entity between two bodies.
+    pub fn insert_joint(&mut self, e: Entity, body_handle1: RigidBodyHandle, body_handle2: RigidBodyHandle, joint: GenericJoint) -> ImpulseJointHandle {
+        let joint_handle = self
+            .impulse_joints
+            .insert(body_handle1, body_handle2, joint, true);
+
+        self.entities_by_joint_handle
+            .insert(joint_handle, e);
+        joint_handle
     }
 
+    /// Removes a body, rapier also removes the joints attached to it.
     pub fn remove_body(&mut self, body_handle: RigidBodyHandle) -> Option<RigidBody> {
+        if let Some(e) = self.entities_by_body_handle.remove(&body_handle) {
+            if self.body_handles_by_entity.get(&e) == Some(&body_handle) {
+                self.body_handles_by_entity.remove(&e);
+            }
+        }
+        for (.., joint_handle, _) in self.impulse_joints.attached_joints(body_handle) {
+            self.entities_by_joint_handle.remove(&joint_handle);
+        }
         self.bodies.remove(
             body_handle,
             &mut self.island_manager,
@@ -111,6 +272,15 @@ impl Physics {
     }
 
     pub fn remove_collider(&mut self, collider_handle: ColliderHandle) -> Option<Collider> {
+        if let Some(e) = self
+            .entities_by_collider_handle
+            .remove(&collider_handle)
+        {
+            if self.collider_handles_by_entity.get(&e) == Some(&collider_handle) {
+                self.collider_handles_by_entity.remove(&e);
+            }
+        }
+        self.one_way_colliders.remove(&collider_handle);
         self.colliders.remove(
             collider_handle,
             &mut self.island_manager,
@@ -119,8 +289,22 @@ impl Physics {
         )
     }
 
+    pub fn set_one_way(&mut self, collider_handle: ColliderHandle, one_way: bool) {
+        match one_way {
+            true => self.one_way_colliders.insert(collider_handle),
+            false => self.one_way_colliders.remove(&collider_handle),
+        };
+    }
+
+    pub fn remove_joint(&mut self, joint_handle: ImpulseJointHandle) -> Option<ImpulseJoint> {
+        self.entities_by_joint_handle
+            .remove(&joint_handle);
+        self.impulse_joints.remove(joint_handle, true)
+    }
+
     //
 
+    #[allow(clippy::too_many_arguments)]
     pub fn move_controller(
         &mut self,
         scaler: &Scaler,
@@ -128,8 +312,10 @@ impl Physics {
         collider_handle: &PhysicsColliderHandle,
         collider_options: Option<&PhysicsColliderOptions>,
         character_controller: &mut PhysicsCharacterController,
+        fraction: f32,
+        merge: bool,
     ) {
-        let (movement, collisions) = {
+        let (movement, collisions, platform_translation, is_overlapping_one_way) = {
             let body = self
                 .bodies
                 .get(body_handle.handle())
@@ -138,33 +324,94 @@ impl Physics {
                 .colliders
                 .get(collider_handle.handle())
                 .expect("Collider not found");
-            let position = body.position();
-            let controller = character_controller.rapier_controller;
+            let position = *body.position();
+            let controller = character_controller.rapier_controller(scaler);
+            let platform_translation = character_controller
+                .platform
+                .and_then(|platform_handle| self.platform_translation(platform_handle, &Point::from(position.translation.vector)))
+                .unwrap_or_else(Vector::zeros);
             let collider_shape = collider.shape();
             let mut collisions = vec![];
-            let mut query_filter = QueryFilter::default().exclude_rigid_body(body_handle.handle());
+            let mut query_filter = QueryFilter::default()
+                .exclude_sensors()
+                .exclude_rigid_body(body_handle.handle());
 
             if let Some(collider_options) = collider_options {
                 query_filter = query_filter.groups(collider_options.collision_groups);
             }
+
+            // One-way colliders only block characters whose bottom is above their top.
+            let up = controller.up.into_inner();
+            let is_dropping_through = character_controller.is_dropping_through();
+            let character_bottom = -support_extent(collider_shape, &position, &-up);
+            let one_way_tolerance = scaler.pixels_to_meters(ONE_WAY_TOLERANCE);
+            let one_way_predicate = |handle: ColliderHandle, collider: &Collider| {
+                !self.one_way_colliders.contains(&handle)
+                    || (!is_dropping_through && support_extent(collider.shape(), collider.position(), &up) <= character_bottom + one_way_tolerance)
+            };
+            let one_way_overlap_predicate = |handle: ColliderHandle, _: &Collider| self.one_way_colliders.contains(&handle);
+
             let movement = controller.move_shape(
                 self.integration_parameters.dt,
                 &self.bodies,
                 &self.colliders,
                 &self.query_pipeline,
                 collider_shape,
-                position,
+                &position,
                 scaler
-                    .pixels_to_meters(character_controller.velocity)
-                    .to_physics(),
-                query_filter,
+                    .pixels_to_meters(character_controller.world_velocity() * fraction)
+                    .to_physics()
+                    + platform_translation,
+                query_filter.predicate(&one_way_predicate),
                 |collision| {
                     collisions.push(collision);
                 },
             );
+            let is_overlapping_one_way = is_dropping_through
+                && self
+                    .query_pipeline
+                    .intersection_with_shape(
+                        &self.bodies,
+                        &self.colliders,
+                        &Isometry::from_parts(
+                            (position.translation.vector + movement.translation).into(),
+                            position.rotation,
+                        ),
+                        collider_shape,
+                        query_filter.predicate(&one_way_overlap_predicate),
+                    )
+                    .is_some();
 
-            (movement, collisions)
+            if let Some(push_mass) = character_controller.options.push_mass {
+                controller.solve_character_collision_impulses(
+                    self.integration_parameters.dt,
+                    &mut self.bodies,
+                    &self.colliders,
+                    &self.query_pipeline,
+                    collider_shape,
+                    push_mass,
+                    &collisions,
+                    query_filter.predicate(&one_way_predicate),
+                );
+            }
+
+            (movement, collisions, platform_translation, is_overlapping_one_way)
         };
+        let is_on_one_way_floor = movement.grounded
+            && collisions.iter().any(|collision| {
+                character_controller.is_floor_collision(collision) && self.one_way_colliders.contains(&collision.handle)
+            });
+        let platform = collisions
+            .iter()
+            .filter(|collision| movement.grounded && character_controller.is_floor_collision(collision))
+            .find_map(|collision| {
+                let platform_handle = self.colliders.get(collision.handle)?.parent()?;
+
+                self.bodies
+                    .get(platform_handle)?
+                    .is_kinematic()
+                    .then_some(platform_handle)
+            });
 
         let body = self
             .bodies
@@ -173,7 +420,39 @@ impl Physics {
         let position = body.position();
 
         body.set_next_kinematic_translation(position.translation.vector + movement.translation);
-        character_controller.update_with_movement(movement, collisions);
+        character_controller.update_with_movement(self, movement, collisions, merge);
+        character_controller.update_with_platform(
+            platform,
+            scaler.meters_to_pixels(platform_translation.to_bevy()) / fraction,
+        );
+        character_controller.update_with_one_way(is_on_one_way_floor, is_overlapping_one_way);
+    }
+
+    /// Returns the translation applied by a kinematic platform during the next step at the given point.
+    fn platform_translation(&self, platform_handle: RigidBodyHandle, point: &Point<Real>) -> Option<Vector<Real>> {
+        let platform = self.bodies.get(platform_handle)?;
+
+        match platform.body_type() {
+            RigidBodyType::KinematicPositionBased => Some(platform.next_position() * platform.position().inverse_transform_point(point) - point),
+            RigidBodyType::KinematicVelocityBased => Some(platform.velocity_at_point(point) * self.integration_parameters.dt),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the furthest extent of the shape along the direction.
+fn support_extent(shape: &dyn Shape, position: &Isometry<Real>, direction: &Vector<Real>) -> Real {
+    match shape.as_support_map() {
+        Some(support_map) => support_map
+            .support_point(position, direction)
+            .coords
+            .dot(direction),
+        None => shape
+            .compute_aabb(position)
+            .vertices()
+            .iter()
+            .map(|vertex| vertex.coords.dot(direction))
+            .fold(Real::MIN, Real::max),
     }
 }
 
@@ -184,8 +463,42 @@ impl Hash for Scaler {
 }
 
 impl Hash for Physics {
+    /// Hashes the state of bodies and the layout of colliders and joints.
+    /// With enhanced determinism any bit difference is a desync, so values are hashed exactly.
+    /// Arenas are iterated in handle order, which matches across peers as handles are created in rollback order.
     #[cfg(feature = "stable")]
-    fn hash<H: std::hash::Hasher>(&self, _: &mut H) {}
+    fn hash<H: std::hash::Hasher>(&self, mut state: &mut H) {
+        self.accumulator.hash(&mut state);
+
+        self.bodies.len().hash(&mut state);
+        for (handle, body) in self.bodies.iter() {
+            let linvel = body.linvel();
+            let rotation = body.rotation().angle();
+            let translation = body.translation();
+
+            handle.hash(&mut state);
+            body.is_sleeping().hash(&mut state);
+            f32_hasher(rotation, &mut state);
+            f32_hasher(translation.x, &mut state);
+            f32_hasher(translation.y, &mut state);
+            f32_hasher(linvel.x, &mut state);
+            f32_hasher(linvel.y, &mut state);
+            f32_hasher(body.angvel(), &mut state);
+        }
+        self.colliders.len().hash(&mut state);
+        for (handle, collider) in self.colliders.iter() {
+            handle.hash(&mut state);
+            collider.parent().hash(&mut state);
+            collider.is_sensor().hash(&mut state);
+            (collider.shape().shape_type() as u8).hash(&mut state);
+        }
+        self.impulse_joints.len().hash(&mut state);
+        for (handle, joint) in self.impulse_joints.iter() {
+            handle.hash(&mut state);
+            joint.body1.hash(&mut state);
+            joint.body2.hash(&mut state);
+        }
+    }
     #[cfg(not(feature = "stable"))]
     fn hash<H: std::hash::Hasher>(&self, mut state: &mut H) {
         for (_, body) in self.bodies.iter() {
@@ -207,6 +520,47 @@ impl Hash for Physics {
     }
 }
 
+impl MapEntities for Physics {
+    /// Follows the entities respawned by a rollback, the index would otherwise point at despawned ones.
+    fn map_entities<E: EntityMapper>(&mut self, entity_mapper: &mut E) {
+        self.body_handles_by_entity = self
+            .body_handles_by_entity
+            .drain()
+            .map(|(e, handle)| (entity_mapper.get_mapped(e), handle))
+            .collect();
+        self.collider_handles_by_entity = self
+            .collider_handles_by_entity
+            .drain()
+            .map(|(e, handle)| (entity_mapper.get_mapped(e), handle))
+            .collect();
+        for e in self
+            .entities_by_body_handle
+            .values_mut()
+            .chain(self.entities_by_collider_handle.values_mut())
+            .chain(self.entities_by_joint_handle.values_mut())
+        {
+            *e = entity_mapper.get_mapped(*e);
+        }
+    }
+}
+
+impl MapEntities for PhysicsRemovals {
+    fn map_entities<E: EntityMapper>(&mut self, entity_mapper: &mut E) {
+        for (e, _) in self.bodies.iter_mut() {
+            *e = entity_mapper.get_mapped(*e);
+        }
+        for (e, _) in self.colliders.iter_mut() {
+            *e = entity_mapper.get_mapped(*e);
+        }
+        for (e, _) in self.joints.iter_mut() {
+            *e = entity_mapper.get_mapped(*e);
+        }
+        for (e, _) in self.forces.iter_mut() {
+            *e = entity_mapper.get_mapped(*e);
+        }
+    }
+}
+
 impl Default for Scaler {
     fn default() -> Self {
         Self { scale: 100.0 }
@@ -220,6 +574,11 @@ impl Default for Physics {
             //
             body_handles_by_entity: default(),
             collider_handles_by_entity: default(),
+            entities_by_body_handle: default(),
+            entities_by_collider_handle: default(),
+            entities_by_joint_handle: default(),
+            //
+            one_way_colliders: default(),
             //
             bodies: default(),
             colliders: default(),
@@ -231,6 +590,24 @@ impl Default for Physics {
             impulse_joints: default(),
             multibody_joints: default(),
             integration_parameters: default(),
+            //
+            timestep: Duration::from_secs_f64(1.0 / 60.0),
+            substeps: 1,
+            accumulator: Duration::ZERO,
+        }
+    }
+}
+
+impl Default for PhysicsWorkspace {
+    fn default() -> Self {
+        let (collision_event_sender, collision_event_receiver) = crossbeam::channel::unbounded();
+        let (contact_force_event_sender, contact_force_event_receiver) = crossbeam::channel::unbounded();
+
+        Self {
+            pipeline: PhysicsPipeline::new(),
+            event_collector: ChannelEventCollector::new(collision_event_sender, contact_force_event_sender),
+            collision_event_receiver,
+            contact_force_event_receiver,
         }
     }
 }
@@ -238,6 +615,7 @@ impl Default for Physics {
 //
 
 #[allow(clippy::type_complexity)]
+#[allow(clippy::too_many_arguments)]
 fn physics_system(
     mut query: Query<(
         &Rollback,
@@ -246,24 +624,113 @@ fn physics_system(
         Option<&PhysicsColliderOptions>,
         &mut PhysicsCharacterController,
     )>,
+    rollbacks: Query<&Rollback>,
     //
+    time: Res<Time<GgrsTime>>,
     order: Res<RollbackOrdered>,
     scaler: Res<Scaler>,
     mut physics: ResMut<Physics>,
+    mut workspace: ResMut<PhysicsWorkspace>,
+    //
+    mut sensor_events: ResMut<RollbackEvents<PhysicsSensorEvent>>,
+    mut collision_events: ResMut<RollbackEvents<PhysicsCollisionEvent>>,
+    mut contact_force_events: ResMut<RollbackEvents<PhysicsContactForceEvent>>,
 ) {
     let mut query = query.iter_mut().collect::<Vec<_>>();
     query.sort_by(|(rollback_a, ..), (rollback_b, ..)| cmp_rollback(&order, rollback_a, rollback_b));
 
-    for (_, body_handle, collider_handle, collider_options, mut character_controller) in query {
-        physics.move_controller(
-            &scaler,
-            body_handle,
-            collider_handle,
-            collider_options,
-            &mut character_controller,
-        );
+    let ticks = physics.accumulate(time.delta());
+    let dt = physics.substep_dt();
+    let steps = ticks * physics.substeps.max(1);
+    // Controller velocities are per frame, each step moves them by its share of the frame.
+    let fraction = match time.delta_secs() > 0.0 {
+        true => dt / time.delta_secs(),
+        false => 1.0,
+    };
+    let mut collisions = vec![];
+    let mut contact_forces = vec![];
+    let mut sensors = vec![];
+
+    physics.integration_parameters.dt = dt;
+    for step in 0..steps {
+        for (_, body_handle, collider_handle, collider_options, character_controller) in query.iter_mut() {
+            physics.move_controller(
+                &scaler,
+                body_handle,
+                collider_handle,
+                *collider_options,
+                character_controller,
+                fraction,
+                step > 0,
+            );
+        }
+
+        let (step_collisions, step_contact_forces) = physics.step(&mut workspace);
+
+        collisions.extend(step_collisions);
+        contact_forces.extend(step_contact_forces);
+    }
+
+    sensor_events.clear();
+    collision_events.clear();
+    contact_force_events.clear();
+    for collision in collisions {
+        // Colliders removed during this step can no longer be mapped back to their entity.
+        let (Some(entity1), Some(entity2)) = (
+            physics.collider_entity(collision.collider1()),
+            physics.collider_entity(collision.collider2()),
+        ) else {
+            continue;
+        };
+
+        if collision.sensor() {
+            let is_sensor = |collider_handle| {
+                physics
+                    .colliders
+                    .get(collider_handle)
+                    .is_some_and(|collider| collider.is_sensor())
+            };
+
+            if is_sensor(collision.collider1()) {
+                sensors.push((collision.started(), entity1, entity2));
+            }
+            if is_sensor(collision.collider2()) {
+                sensors.push((collision.started(), entity2, entity1));
+            }
+            continue;
+        }
+        collision_events.push(match collision.started() {
+            true => PhysicsCollisionEvent::Started { entity1, entity2 },
+            false => PhysicsCollisionEvent::Stopped { entity1, entity2 },
+        });
+    }
+
+    let rollback_order = |e| rollbacks.get(e).ok().map(|rollback| order.order(*rollback));
+
+    sensors.sort_by_key(|(_, sensor, entity)| (rollback_order(*sensor), rollback_order(*entity)));
+    for (started, sensor, entity) in sensors {
+        sensor_events.push(match started {
+            true => PhysicsSensorEvent::Entered { sensor, entity },
+            false => PhysicsSensorEvent::Exited { sensor, entity },
+        });
+    }
+    for contact_force in contact_forces {
+        let (Some(entity1), Some(entity2)) = (
+            physics.collider_entity(contact_force.collider1),
+            physics.collider_entity(contact_force.collider2),
+        ) else {
+            continue;
+        };
+
+        contact_force_events.push(PhysicsContactForceEvent {
+            entity1,
+            entity2,
+            total_force: scaler.meters_to_pixels(contact_force.total_force.to_bevy()),
+            total_force_magnitude: scaler.meters_to_pixels(contact_force.total_force_magnitude),
+            max_force_direction: contact_force.max_force_direction.to_bevy(),
+            max_force_magnitude: scaler.meters_to_pixels(contact_force.max_force_magnitude),
+        });
     }
-    physics.step();
 }
 
 #[allow(clippy::type_complexity)]
@@ -285,7 +752,98 @@ fn physics_sync_system(
     }
 }
 
+fn physics_impulse_system(
+    query: Query<(Entity, &Rollback, &PhysicsBodyHandle, &PhysicsImpulse)>,
+    mut commands: Commands,
+    //
+    order: Res<RollbackOrdered>,
+    scaler: Res<Scaler>,
+    mut physics: ResMut<Physics>,
+) {
+    let mut query = query.iter().collect::<Vec<_>>();
+    query.sort_by(|(_, rollback_a, ..), (_, rollback_b, ..)| cmp_rollback(&order, rollback_a, rollback_b));
+
+    for (e, _, body_handle, impulse) in query {
+        if let Some(body) = physics.bodies.get_mut(body_handle.handle()) {
+            impulse.apply(&scaler, body);
+        }
+        commands.entity(e).remove::<PhysicsImpulse>();
+    }
+}
+
+fn physics_force_system(
+    query: Query<(&Rollback, &PhysicsBodyHandle, &PhysicsForce)>,
+    query_forces: Query<(), With<PhysicsForce>>,
+    //
+    order: Res<RollbackOrdered>,
+    scaler: Res<Scaler>,
+    mut removals: ResMut<PhysicsRemovals>,
+    mut physics: ResMut<Physics>,
+) {
+    // Forces outlive the component in rapier, clear them once it is removed unless it was added back since.
+    let mut reset_body_handles = removals
+        .forces
+        .drain(..)
+        .filter(|(e, handle)| physics.body_entity(*handle) == Some(*e) && !query_forces.contains(*e))
+        .map(|(_, handle)| handle)
+        .collect::<Vec<_>>();
+
+    reset_body_handles.sort_by_key(|handle| handle.into_raw_parts());
+    reset_body_handles.dedup();
+
+    for handle in reset_body_handles {
+        if let Some(body) = physics.bodies.get_mut(handle) {
+            body.reset_forces(false);
+            body.reset_torques(false);
+        }
+    }
+
+    let mut query = query.iter().collect::<Vec<_>>();
+    query.sort_by(|(rollback_a, ..), (rollback_b, ..)| cmp_rollback(&order, rollback_a, rollback_b));
+
+    for (_, body_handle, force) in query {
+        if let Some(body) = physics.bodies.get_mut(body_handle.handle()) {
+            force.apply(&scaler, body);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn physics_teleport_system(
+    mut query: Query<(
+        Entity,
+        &Rollback,
+        &PhysicsBodyHandle,
+        &PhysicsTeleport,
+        Option<&mut PhysicsCharacterController>,
+    )>,
+    mut commands: Commands,
+    //
+    order: Res<RollbackOrdered>,
+    scaler: Res<Scaler>,
+    mut physics: ResMut<Physics>,
+) {
+    let mut query = query.iter_mut().collect::<Vec<_>>();
+    query.sort_by(|(_, rollback_a, ..), (_, rollback_b, ..)| cmp_rollback(&order, rollback_a, rollback_b));
+
+    for (e, _, body_handle, teleport, character_controller) in query {
+        if let Some(body) = physics.bodies.get_mut(body_handle.handle()) {
+            teleport.apply(&scaler, body);
+        }
+        // The character no longer stands on its platform, it must not inherit its motion when leaving it.
+        if let Some(mut character_controller) = character_controller {
+            character_controller.platform = None;
+            character_controller.platform_velocity = Vec2::ZERO;
+            if teleport.reset_velocity {
+                character_controller.velocity = Vec2::ZERO;
+            }
+        }
+        commands.entity(e).remove::<PhysicsTeleport>();
+    }
+}
+
 #[allow(clippy::type_complexity)]
+#[allow(clippy::too_many_arguments)]
 fn physics_update_system(
     body_query: Query<(
         &Rollback,
@@ -295,9 +853,10 @@ fn physics_update_system(
     )>,
     collider_query: Query<(
         &Rollback,
-        &PhysicsCollider,
+        Ref<PhysicsCollider>,
         &PhysicsColliderHandle,
         &PhysicsColliderOptions,
+        Option<&PhysicsColliderMaterial>,
     )>,
     velocity_query: Query<(
         &Rollback,
@@ -305,18 +864,28 @@ fn physics_update_system(
         &PhysicsBodyHandle,
         &PhysicsBodyVelocity,
     )>,
+    mut child_collider_query: Query<(
+        Entity,
+        &Rollback,
+        &PhysicsBodyHandle,
+        Ref<PhysicsChildColliders>,
+        &mut PhysicsChildColliderHandles,
+    )>,
     //
     order: Res<RollbackOrdered>,
     scaler: Res<Scaler>,
+    materials: Res<Assets<PhysicsMaterial>>,
     mut physics: ResMut<Physics>,
 ) {
     let mut body_query = body_query.iter().collect::<Vec<_>>();
     let mut collider_query = collider_query.iter().collect::<Vec<_>>();
     let mut velocity_query = velocity_query.iter().collect::<Vec<_>>();
+    let mut child_collider_query = child_collider_query.iter_mut().collect::<Vec<_>>();
 
     body_query.sort_by(|(rollback_a, ..), (rollback_b, ..)| cmp_rollback(&order, rollback_a, rollback_b));
     collider_query.sort_by(|(rollback_a, ..), (rollback_b, ..)| cmp_rollback(&order, rollback_a, rollback_b));
     velocity_query.sort_by(|(rollback_a, ..), (rollback_b, ..)| cmp_rollback(&order, rollback_a, rollback_b));
+    child_collider_query.sort_by(|(_, rollback_a, ..), (_, rollback_b, ..)| cmp_rollback(&order, rollback_a, rollback_b));
 
     for (_, body, body_handle, body_options) in body_query {
         body.apply_options(
@@ -338,15 +907,63 @@ fn physics_update_system(
             body_velocity,
         );
     }
-    for (_, collider, collider_handle, collider_options) in collider_query {
-        collider.apply_options(
-            &scaler,
-            physics
+    for (_, collider, collider_handle, collider_options, collider_material) in collider_query {
+        let rapier_collider = physics
+            .colliders
+            .get_mut(collider_handle.handle())
+            .expect("Collider not found");
+
+        // Unchanged descriptions cannot have been resized, skip building their shape.
+        if collider.is_changed() {
+            collider.apply_shape(&scaler, rapier_collider);
+        }
+        collider.apply_options(&scaler, rapier_collider, collider_options);
+        if let Some(material) = collider_material.and_then(|material| materials.get(&material.0)) {
+            material.apply(rapier_collider);
+        }
+        physics.set_one_way(collider_handle.handle(), collider_options.one_way);
+    }
+    for (e, _, body_handle, child_colliders, mut child_collider_handles) in child_collider_query {
+        // Children are matched with their handles by index, removed ones are dropped from the end and added ones appended.
+        if child_colliders.is_changed() && child_colliders.0.len() != child_collider_handles.handles().len() {
+            while child_collider_handles.handles().len() > child_colliders.0.len() {
+                if let Some(child_collider_handle) = child_collider_handles.0.pop() {
+                    physics.remove_collider(child_collider_handle);
+                }
+            }
+            for child_collider in child_colliders.0[child_collider_handles.handles().len()..].iter() {
+                let child_collider_handle = physics.insert_collider(e, child_collider.build(&scaler), body_handle.handle());
+
+                child_collider_handles.0.push(child_collider_handle);
+            }
+        }
+
+        for (child_collider, child_collider_handle) in child_colliders
+            .0
+            .iter()
+            .zip(child_collider_handles.handles())
+        {
+            let rapier_collider = physics
                 .colliders
-                .get_mut(collider_handle.handle())
-                .expect("Collider not found"),
-            collider_options,
-        );
+                .get_mut(*child_collider_handle)
+                .expect("Collider not found");
+
+            if child_colliders.is_changed() {
+                child_collider.apply_shape(&scaler, rapier_collider);
+                child_collider.apply_position(&scaler, rapier_collider);
+            }
+            child_collider
+                .collider
+                .apply_options(&scaler, rapier_collider, &child_collider.options);
+            if let Some(material) = child_collider
+                .material
+                .as_ref()
+                .and_then(|material| materials.get(material))
+            {
+                material.apply(rapier_collider);
+            }
+            physics.set_one_way(*child_collider_handle, child_collider.options.one_way);
+        }
     }
 }
 
@@ -359,6 +976,7 @@ fn physics_create_handles_system(
             &Transform,
             &PhysicsBody,
             &PhysicsCollider,
+            Option<&PhysicsChildColliders>,
         ),
         (
             Without<PhysicsBodyHandle>,
@@ -374,91 +992,158 @@ fn physics_create_handles_system(
     let mut query = query.iter().collect::<Vec<_>>();
     query.sort_by(|(_, rollback_a, ..), (_, rollback_b, ..)| cmp_rollback(&order, rollback_a, rollback_b));
 
-    for (e, _, transform, body, collider) in query {
+    for (e, _, transform, body, collider, child_colliders) in query {
         let body = body.build(&scaler, transform);
         let collider = collider.build(&scaler);
-        let (body_handle, collider_handle) = physics.insert_body(body, collider);
+        let (body_handle, collider_handle) = physics.insert_body(e, body, collider);
 
-        physics
-            .body_handles_by_entity
-            .insert(e, body_handle);
-        physics
-            .collider_handles_by_entity
-            .insert(e, collider_handle);
         commands.entity(e).insert((
             PhysicsBodyHandle(body_handle),
             PhysicsColliderHandle(collider_handle),
         ));
+
+        if let Some(child_colliders) = child_colliders {
+            let child_collider_handles = child_colliders
+                .0
+                .iter()
+                .map(|child_collider| physics.insert_collider(e, child_collider.build(&scaler), body_handle))
+                .collect();
+
+            commands
+                .entity(e)
+                .insert(PhysicsChildColliderHandles(child_collider_handles));
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn physics_create_joint_handles_system(
+    query: Query<
+        (
+            Entity,
+            &Rollback,
+            &PhysicsJoint,
+            &PhysicsBodyHandle,
+        ),
+        Without<PhysicsJointHandle>,
+    >,
+    query_targets: Query<&PhysicsBodyHandle>,
+    mut commands: Commands,
+    //
+    order: Res<RollbackOrdered>,
+    scaler: Res<Scaler>,
+    mut physics: ResMut<Physics>,
+) {
+    let mut query = query.iter().collect::<Vec<_>>();
+    query.sort_by(|(_, rollback_a, ..), (_, rollback_b, ..)| cmp_rollback(&order, rollback_a, rollback_b));
+
+    for (e, _, joint, body_handle) in query {
+        // The target body may not have been created yet, try again next frame.
+        let Ok(target_body_handle) = query_targets.get(joint.target) else {
+            continue;
+        };
+        let joint_handle = physics.insert_joint(
+            e,
+            body_handle.handle(),
+            target_body_handle.handle(),
+            joint.build(&scaler),
+        );
+
+        commands
+            .entity(e)
+            .insert(PhysicsJointHandle(joint_handle));
     }
 }
 
 #[allow(clippy::type_complexity)]
 fn physics_remove_handles_system(
     query_body_handles: Query<&PhysicsBodyHandle>,
+    query_joint_handles: Query<&PhysicsJointHandle>,
     query_collider_handles: Query<&PhysicsColliderHandle>,
+    query_child_collider_handles: Query<&PhysicsChildColliderHandles>,
+    mut commands: Commands,
     //
+    mut removals: ResMut<PhysicsRemovals>,
     mut physics: ResMut<Physics>,
 ) {
-    let body_handles = query_body_handles
-        .iter()
-        .map(|b| b.handle())
-        .collect::<HashSet<_>>();
-    let collider_handles = query_collider_handles
-        .iter()
-        .map(|c| c.handle())
-        .collect::<HashSet<_>>();
-    let mut remove_body_handles = vec![];
-    let mut remove_collider_handles = vec![];
-
-    for (handle, _) in physics.bodies.iter() {
-        if !body_handles.contains(&handle) {
-            remove_body_handles.push(handle);
-        }
-    }
-    for (handle, _) in physics.colliders.iter() {
-        if !collider_handles.contains(&handle) {
-            remove_collider_handles.push(handle);
-        }
+    // Handles queued before this world was created belong to another session.
+    if physics.is_added() {
+        removals.clear();
     }
 
-    for handle in remove_body_handles {
-        physics
-            .remove_body(handle)
-            .expect("Body not removed");
-    }
-    for handle in remove_collider_handles {
-        physics
-            .remove_collider(handle)
-            .expect("Collider not removed");
-    }
-}
+    // Hooks also fire when a rollback restores entities without their handles, such handles are not in the restored world.
+    // Handles still held by their entity were restored along with it and are kept.
+    let mut remove_joint_handles = removals
+        .joints
+        .drain(..)
+        .filter(|(e, handle)| {
+            physics.joint_entity(*handle) == Some(*e)
+                && !query_joint_handles
+                    .get(*e)
+                    .is_ok_and(|joint_handle| joint_handle.handle() == *handle)
+        })
+        .map(|(_, handle)| handle)
+        .collect::<Vec<_>>();
+    let mut remove_body_handles = removals
+        .bodies
+        .drain(..)
+        .filter(|(e, handle)| {
+            physics.body_entity(*handle) == Some(*e)
+                && !query_body_handles
+                    .get(*e)
+                    .is_ok_and(|body_handle| body_handle.handle() == *handle)
+        })
+        .map(|(_, handle)| handle)
+        .collect::<Vec<_>>();
+    let mut remove_collider_handles = removals
+        .colliders
+        .drain(..)
+        .filter(|(e, handle)| {
+            physics.collider_entity(*handle) == Some(*e)
+                && !query_collider_handles
+                    .get(*e)
+                    .is_ok_and(|collider_handle| collider_handle.handle() == *handle)
+                && !query_child_collider_handles
+                    .get(*e)
+                    .is_ok_and(|child_collider_handles| child_collider_handles.handles().contains(handle))
+        })
+        .map(|(_, handle)| handle)
+        .collect::<Vec<_>>();
 
-//
+    // Hooks run in despawn order, which differs between peers restoring different frames, so handles are removed in arena order.
+    remove_joint_handles.sort_by_key(|handle| handle.into_raw_parts());
+    remove_joint_handles.dedup();
+    remove_body_handles.sort_by_key(|handle| handle.into_raw_parts());
+    remove_body_handles.dedup();
+    remove_collider_handles.sort_by_key(|handle| handle.into_raw_parts());
+    remove_collider_handles.dedup();
 
-#[allow(clippy::type_complexity)]
-fn physics_debug_system(mut gizmos: Gizmos, scaler: Res<Scaler>, physics: Res<Physics>) {
-    for (_, collider) in physics.colliders.iter() {
-        if let Some(ball) = collider.shape().as_ball() {
-            gizmos.circle_2d(
-                Isometry2d {
-                    translation: scaler.meters_to_pixels(collider.translation().to_bevy()),
-                    ..default()
-                },
-                scaler.meters_to_pixels(ball.radius),
-                Color::linear_rgba(1.0, 0.0, 1.0, 0.2),
-            );
-        }
-        if let Some(cuboid) = collider.shape().as_cuboid() {
-            gizmos.rect_2d(
-                Isometry2d {
-                    rotation: ToBevyRot2Ext::to_bevy(collider.rotation()),
-                    translation: scaler.meters_to_pixels(collider.translation().to_bevy()),
-                },
-                scaler.meters_to_pixels(cuboid.half_extents.to_bevy()) * 2.0,
-                Color::linear_rgba(0.0, 1.0, 0.0, 0.2),
-            );
+    for handle in remove_joint_handles {
+        physics.remove_joint(handle);
+    }
+    for handle in remove_body_handles {
+        // Joints are also removed by rapier along with either of their bodies.
+        let joint_entities = physics
+            .impulse_joints
+            .attached_joints(handle)
+            .filter_map(|(.., joint_handle, _)| Some((physics.joint_entity(joint_handle)?, joint_handle)))
+            .collect::<Vec<_>>();
+
+        physics.remove_body(handle);
+        for (e, joint_handle) in joint_entities {
+            if query_joint_handles
+                .get(e)
+                .is_ok_and(|handle| handle.handle() == joint_handle)
+            {
+                commands
+                    .entity(e)
+                    .remove::<PhysicsJointHandle>();
+            }
         }
     }
+    for handle in remove_collider_handles {
+        physics.remove_collider(handle);
+    }
 }
 
 //
@@ -466,15 +1151,24 @@ fn physics_debug_system(mut gizmos: Gizmos, scaler: Res<Scaler>, physics: Res<Ph
 pub fn physics_systems() -> ScheduleConfigs<ScheduleSystem> {
     (
         physics_create_handles_system,
+        physics_create_joint_handles_system,
         physics_remove_handles_system,
         physics_update_system,
+        physics_impulse_system,
+        physics_force_system,
+        physics_teleport_system,
+        physics_gravity_system,
+        physics_gravity_well_system,
         physics_sync_system,
         physics_system,
+        physics_controller_material_system,
     )
         .chain()
         .into_configs()
 }
 
 pub fn physics_debug_systems() -> ScheduleConfigs<ScheduleSystem> {
-    physics_debug_system.into_configs()
+    (physics_debug_toggle_system, physics_debug_system)
+        .chain()
+        .into_configs()
 }
@@ -0,0 +1,285 @@
+use std::cmp::Ordering;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_ggrs::{Rollback, RollbackOrdered};
+use rapier2d::math::Real;
+use rapier2d::parry::query::{RayIntersection, ShapeCastOptions};
+use rapier2d::prelude::*;
+
+use crate::physics::collider::PhysicsCollider;
+use crate::physics::{Physics, Scaler};
+use crate::utilities::maths::*;
+
+#[derive(Copy, Clone)]
+pub struct PhysicsQueryFilter {
+    pub groups: InteractionGroups,
+    pub exclude: Option<Entity>,
+    pub exclude_sensors: bool,
+}
+
+#[derive(Copy, Clone)]
+pub struct PhysicsRayHit {
+    pub entity: Entity,
+    pub point: Vec2,
+    pub normal: Vec2,
+    pub distance: f32,
+}
+
+#[derive(Copy, Clone)]
+pub struct PhysicsShapeHit {
+    pub entity: Entity,
+    pub point: Vec2,
+    pub normal: Vec2,
+    pub distance: f32,
+}
+
+#[derive(Copy, Clone)]
+pub struct PhysicsPointProjection {
+    pub entity: Entity,
+    pub point: Vec2,
+    pub is_inside: bool,
+}
+
+/// Scene queries against the [`Physics`] world in pixel units.
+/// Every query returning several entities sorts them with the order resource managed by [`bevy_ggrs`].
+#[derive(SystemParam)]
+pub struct PhysicsQuery<'w, 's> {
+    rollbacks: Query<'w, 's, &'static Rollback>,
+    //
+    order: Res<'w, RollbackOrdered>,
+    scaler: Res<'w, Scaler>,
+    physics: Res<'w, Physics>,
+}
+
+impl PhysicsQueryFilter {
+    pub fn from_groups(groups: InteractionGroups) -> Self {
+        Self { groups, ..default() }
+    }
+
+    pub fn from_filter(filter: Group) -> Self {
+        Self {
+            groups: InteractionGroups::new(Group::ALL, filter),
+            ..default()
+        }
+    }
+
+    pub fn with_exclude(mut self, entity: Entity) -> Self {
+        self.exclude = Some(entity);
+        self
+    }
+
+    pub fn with_sensors(mut self, sensors: bool) -> Self {
+        self.exclude_sensors = !sensors;
+        self
+    }
+}
+
+impl Default for PhysicsQueryFilter {
+    fn default() -> Self {
+        Self {
+            groups: InteractionGroups::all(),
+            exclude: None,
+            exclude_sensors: true,
+        }
+    }
+}
+
+impl PhysicsQuery<'_, '_> {
+    /// Returns the closest hit along the ray.
+    pub fn cast_ray(&self, origin: Vec2, direction: Vec2, max_distance: f32, filter: PhysicsQueryFilter) -> Option<PhysicsRayHit> {
+        let ray = self.ray(origin, direction);
+
+        self.physics
+            .query_pipeline
+            .cast_ray_and_get_normal(
+                &self.physics.bodies,
+                &self.physics.colliders,
+                &ray,
+                self.scaler.pixels_to_meters(max_distance),
+                true,
+                self.query_filter(&filter),
+            )
+            .and_then(|(collider_handle, intersection)| self.ray_hit(&ray, collider_handle, intersection))
+    }
+
+    /// Returns every hit along the ray, sorted by distance.
+    pub fn cast_ray_all(&self, origin: Vec2, direction: Vec2, max_distance: f32, filter: PhysicsQueryFilter) -> Vec<PhysicsRayHit> {
+        let ray = self.ray(origin, direction);
+        let mut hits = vec![];
+
+        self.physics
+            .query_pipeline
+            .intersections_with_ray(
+                &self.physics.bodies,
+                &self.physics.colliders,
+                &ray,
+                self.scaler.pixels_to_meters(max_distance),
+                true,
+                self.query_filter(&filter),
+                |collider_handle, intersection| {
+                    hits.extend(self.ray_hit(&ray, collider_handle, intersection));
+                    true
+                },
+            );
+        hits.sort_by(|hit_a, hit_b| {
+            hit_a
+                .distance
+                .total_cmp(&hit_b.distance)
+                .then_with(|| self.cmp_entities(hit_a.entity, hit_b.entity))
+        });
+        hits
+    }
+
+    /// Returns the first hit of the shape moving along the direction.
+    pub fn cast_shape(
+        &self,
+        shape: &PhysicsCollider,
+        rotation: f32,
+        translation: Vec2,
+        direction: Vec2,
+        max_distance: f32,
+        filter: PhysicsQueryFilter,
+    ) -> Option<PhysicsShapeHit> {
+        let shape = shape.shape(&self.scaler);
+        let position = self.isometry(rotation, translation);
+
+        self.physics
+            .query_pipeline
+            .cast_shape(
+                &self.physics.bodies,
+                &self.physics.colliders,
+                &position,
+                &direction.normalize_or_zero().to_physics(),
+                &*shape,
+                ShapeCastOptions::with_max_time_of_impact(self.scaler.pixels_to_meters(max_distance)),
+                self.query_filter(&filter),
+            )
+            .and_then(|(collider_handle, hit)| {
+                let collider = self.physics.colliders.get(collider_handle)?;
+
+                Some(PhysicsShapeHit {
+                    entity: self.physics.collider_entity(collider_handle)?,
+                    point: self
+                        .scaler
+                        .meters_to_pixels((collider.position() * hit.witness1).coords.to_bevy()),
+                    normal: (collider.position() * hit.normal1).to_bevy(),
+                    distance: self
+                        .scaler
+                        .meters_to_pixels(hit.time_of_impact),
+                })
+            })
+    }
+
+    /// Returns the closest point on the closest collider.
+    pub fn project_point(&self, point: Vec2, filter: PhysicsQueryFilter) -> Option<PhysicsPointProjection> {
+        self.physics
+            .query_pipeline
+            .project_point(
+                &self.physics.bodies,
+                &self.physics.colliders,
+                &Point::from(self.scaler.pixels_to_meters(point).to_physics()),
+                true,
+                self.query_filter(&filter),
+            )
+            .and_then(|(collider_handle, projection)| {
+                Some(PhysicsPointProjection {
+                    entity: self.physics.collider_entity(collider_handle)?,
+                    point: self
+                        .scaler
+                        .meters_to_pixels(projection.point.coords.to_bevy()),
+                    is_inside: projection.is_inside,
+                })
+            })
+    }
+
+    /// Returns every entity overlapping the circle.
+    pub fn overlap_circle(&self, center: Vec2, radius: f32, filter: PhysicsQueryFilter) -> Vec<Entity> {
+        self.overlap_shape(&PhysicsCollider::Circle { radius }, 0.0, center, filter)
+    }
+
+    /// Returns every entity overlapping the shape.
+    pub fn overlap_shape(&self, shape: &PhysicsCollider, rotation: f32, translation: Vec2, filter: PhysicsQueryFilter) -> Vec<Entity> {
+        let shape = shape.shape(&self.scaler);
+        let position = self.isometry(rotation, translation);
+        let mut entities = vec![];
+
+        self.physics
+            .query_pipeline
+            .intersections_with_shape(
+                &self.physics.bodies,
+                &self.physics.colliders,
+                &position,
+                &*shape,
+                self.query_filter(&filter),
+                |collider_handle| {
+                    entities.extend(self.physics.collider_entity(collider_handle));
+                    true
+                },
+            );
+        entities.sort_by(|entity_a, entity_b| self.cmp_entities(*entity_a, *entity_b));
+        entities.dedup();
+        entities
+    }
+
+    //
+
+    fn ray(&self, origin: Vec2, direction: Vec2) -> Ray {
+        Ray::new(
+            Point::from(self.scaler.pixels_to_meters(origin).to_physics()),
+            direction.normalize_or_zero().to_physics(),
+        )
+    }
+
+    fn ray_hit(&self, ray: &Ray, collider_handle: ColliderHandle, intersection: RayIntersection) -> Option<PhysicsRayHit> {
+        Some(PhysicsRayHit {
+            entity: self.physics.collider_entity(collider_handle)?,
+            point: self.scaler.meters_to_pixels(
+                ray.point_at(intersection.time_of_impact)
+                    .coords
+                    .to_bevy(),
+            ),
+            normal: intersection.normal.to_bevy(),
+            distance: self
+                .scaler
+                .meters_to_pixels(intersection.time_of_impact),
+        })
+    }
+
+    fn isometry(&self, rotation: f32, translation: Vec2) -> Isometry<Real> {
+        Isometry::new(
+            self.scaler
+                .pixels_to_meters(translation)
+                .to_physics(),
+            rotation,
+        )
+    }
+
+    fn query_filter(&self, filter: &PhysicsQueryFilter) -> QueryFilter<'_> {
+        let mut query_filter = QueryFilter::default().groups(filter.groups);
+
+        if filter.exclude_sensors {
+            query_filter = query_filter.exclude_sensors();
+        }
+        if let Some(body_handle) = filter
+            .exclude
+            .and_then(|entity| self.physics.entity_body(entity))
+        {
+            query_filter = query_filter.exclude_rigid_body(body_handle);
+        }
+        query_filter
+    }
+
+    fn cmp_entities(&self, entity_a: Entity, entity_b: Entity) -> Ordering {
+        let order = |entity| {
+            self.rollbacks
+                .get(entity)
+                .ok()
+                .map(|rollback| self.order.order(*rollback))
+        };
+
+        order(entity_a)
+            .cmp(&order(entity_b))
+            .then_with(|| entity_a.cmp(&entity_b))
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::ensure;
+use bevy::prelude::*;
+use bincode::Options;
+use rapier2d::math::Real;
+use rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::physics::Physics;
+
+const SNAPSHOT_MAGIC: [u8; 4] = *b"SWPH";
+const SNAPSHOT_VERSION: u32 = 1;
+/// Largest snapshot accepted, bytes may come from a peer and length prefixes must not allocate without bound.
+const SNAPSHOT_SIZE_LIMIT: u64 = 64 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize)]
+struct PhysicsSnapshotHeader {
+    magic: [u8; 4],
+    version: u32,
+}
+
+/// Copy of the [`Physics`] state needed to resimulate it.
+/// The query pipeline and the CCD solver are derived from it and rebuilt when restored.
+/// Entities are stored as stable ids, entity ids only mean something in the process that created them.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PhysicsSnapshot {
+    gravity: Vector<Real>,
+    //
+    bodies: RigidBodySet,
+    colliders: ColliderSet,
+    broad_phase: DefaultBroadPhase,
+    narrow_phase: NarrowPhase,
+    island_manager: IslandManager,
+    impulse_joints: ImpulseJointSet,
+    multibody_joints: MultibodyJointSet,
+    integration_parameters: IntegrationParameters,
+    //
+    timestep: Duration,
+    substeps: usize,
+    accumulator: Duration,
+    //
+    body_handles_by_id: Vec<(u64, RigidBodyHandle)>,
+    collider_handles_by_id: Vec<(u64, ColliderHandle)>,
+    ids_by_body_handle: Vec<(RigidBodyHandle, u64)>,
+    ids_by_collider_handle: Vec<(ColliderHandle, u64)>,
+    ids_by_joint_handle: Vec<(ImpulseJointHandle, u64)>,
+    //
+    one_way_colliders: Vec<ColliderHandle>,
+}
+
+fn options() -> impl Options {
+    bincode::options()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(SNAPSHOT_SIZE_LIMIT)
+}
+
+impl Physics {
+    /// Serializes the physics world into a versioned binary snapshot.
+    /// Entities are written as the stable ids returned by `ids`, such as their rollback order, entities without one are left out.
+    pub fn to_bytes(&self, ids: impl Fn(Entity) -> Option<u64>) -> Result<Vec<u8>, anyhow::Error> {
+        let mut bytes = options().serialize(&PhysicsSnapshotHeader {
+            magic: SNAPSHOT_MAGIC,
+            version: SNAPSHOT_VERSION,
+        })?;
+
+        options().serialize_into(&mut bytes, &PhysicsSnapshot::new(self, ids))?;
+        Ok(bytes)
+    }
+
+    /// Deserializes a physics world from a binary snapshot created by [`Physics::to_bytes`].
+    /// Stable ids are turned back into entities by `entities`, ids without one are left out.
+    pub fn from_bytes(mut bytes: &[u8], entities: impl Fn(u64) -> Option<Entity>) -> Result<Self, anyhow::Error> {
+        let header: PhysicsSnapshotHeader = options().deserialize_from(&mut bytes)?;
+
+        ensure!(
+            header.magic == SNAPSHOT_MAGIC,
+            "Not a physics snapshot"
+        );
+        ensure!(
+            header.version == SNAPSHOT_VERSION,
+            "Unsupported physics snapshot version {}, expected {}",
+            header.version,
+            SNAPSHOT_VERSION
+        );
+
+        let snapshot: PhysicsSnapshot = options().deserialize_from(&mut bytes)?;
+
+        Ok(snapshot.into_physics(entities))
+    }
+}
+
+impl PhysicsSnapshot {
+    fn new(physics: &Physics, ids: impl Fn(Entity) -> Option<u64>) -> Self {
+        let mut one_way_colliders = physics
+            .one_way_colliders
+            .iter()
+            .copied()
+            .collect::<Vec<_>>();
+
+        one_way_colliders.sort_by_key(|handle| handle.into_raw_parts());
+
+        Self {
+            gravity: physics.gravity,
+            //
+            bodies: physics.bodies.clone(),
+            colliders: physics.colliders.clone(),
+            broad_phase: physics.broad_phase.clone(),
+            narrow_phase: physics.narrow_phase.clone(),
+            island_manager: physics.island_manager.clone(),
+            impulse_joints: physics.impulse_joints.clone(),
+            multibody_joints: physics.multibody_joints.clone(),
+            integration_parameters: physics.integration_parameters,
+            //
+            timestep: physics.timestep,
+            substeps: physics.substeps,
+            accumulator: physics.accumulator,
+            //
+            body_handles_by_id: ids_by_entity(&physics.body_handles_by_entity, &ids),
+            collider_handles_by_id: ids_by_entity(&physics.collider_handles_by_entity, &ids),
+            ids_by_body_handle: ids_by_handle(&physics.entities_by_body_handle, &ids, |handle| handle.into_raw_parts()),
+            ids_by_collider_handle: ids_by_handle(&physics.entities_by_collider_handle, &ids, |handle| handle.into_raw_parts()),
+            ids_by_joint_handle: ids_by_handle(&physics.entities_by_joint_handle, &ids, |handle| handle.into_raw_parts()),
+            //
+            one_way_colliders,
+        }
+    }
+
+    fn into_physics(self, entities: impl Fn(u64) -> Option<Entity>) -> Physics {
+        let mut query_pipeline = QueryPipeline::new();
+
+        query_pipeline.update(&self.colliders);
+
+        Physics {
+            gravity: self.gravity,
+            //
+            bodies: self.bodies,
+            colliders: self.colliders,
+            ccd_solver: default(),
+            broad_phase: self.broad_phase,
+            narrow_phase: self.narrow_phase,
+            query_pipeline,
+            island_manager: self.island_manager,
+            impulse_joints: self.impulse_joints,
+            multibody_joints: self.multibody_joints,
+            integration_parameters: self.integration_parameters,
+            //
+            timestep: self.timestep,
+            substeps: self.substeps,
+            accumulator: self.accumulator,
+            //
+            body_handles_by_entity: self
+                .body_handles_by_id
+                .into_iter()
+                .filter_map(|(id, handle)| Some((entities(id)?, handle)))
+                .collect(),
+            collider_handles_by_entity: self
+                .collider_handles_by_id
+                .into_iter()
+                .filter_map(|(id, handle)| Some((entities(id)?, handle)))
+                .collect(),
+            entities_by_body_handle: self
+                .ids_by_body_handle
+                .into_iter()
+                .filter_map(|(handle, id)| Some((handle, entities(id)?)))
+                .collect(),
+            entities_by_collider_handle: self
+                .ids_by_collider_handle
+                .into_iter()
+                .filter_map(|(handle, id)| Some((handle, entities(id)?)))
+                .collect(),
+            entities_by_joint_handle: self
+                .ids_by_joint_handle
+                .into_iter()
+                .filter_map(|(handle, id)| Some((handle, entities(id)?)))
+                .collect(),
+            //
+            one_way_colliders: self
+                .one_way_colliders
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+/// Hash maps iterate in random order, entries are sorted so equal worlds produce equal bytes.
+fn ids_by_entity<H: Copy>(handles_by_entity: &HashMap<Entity, H>, ids: impl Fn(Entity) -> Option<u64>) -> Vec<(u64, H)> {
+    let mut handles_by_id = handles_by_entity
+        .iter()
+        .filter_map(|(e, handle)| Some((ids(*e)?, *handle)))
+        .collect::<Vec<_>>();
+
+    handles_by_id.sort_by_key(|(id, _)| *id);
+    handles_by_id
+}
+
+fn ids_by_handle<H: Copy, K: Ord>(
+    entities_by_handle: &HashMap<H, Entity>,
+    ids: impl Fn(Entity) -> Option<u64>,
+    key: impl Fn(&H) -> K,
+) -> Vec<(H, u64)> {
+    let mut ids_by_handle = entities_by_handle
+        .iter()
+        .filter_map(|(handle, e)| Some((*handle, ids(*e)?)))
+        .collect::<Vec<_>>();
+
+    ids_by_handle.sort_by_key(|(handle, _)| key(handle));
+    ids_by_handle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_keeps_entity_index() {
+        let mut physics = Physics::default();
+        let e1 = Entity::from_raw(1);
+        let e2 = Entity::from_raw(2);
+        let e3 = Entity::from_raw(3);
+
+        let (body_handle1, collider_handle1) = physics.insert_body(
+            e1,
+            RigidBodyBuilder::dynamic().build(),
+            ColliderBuilder::ball(0.5).build(),
+        );
+        let (body_handle2, _) = physics.insert_body(
+            e2,
+            RigidBodyBuilder::fixed().build(),
+            ColliderBuilder::cuboid(1.0, 0.1).build(),
+        );
+        physics.insert_joint(e3, body_handle1, body_handle2, FixedJointBuilder::new().build().into());
+        physics.one_way_colliders.insert(collider_handle1);
+
+        let bytes = physics
+            .to_bytes(|e| Some(e.index() as u64))
+            .unwrap();
+        let restored = Physics::from_bytes(&bytes, |id| Some(Entity::from_raw(id as u32))).unwrap();
+
+        assert_eq!(restored.body_handles_by_entity, physics.body_handles_by_entity);
+        assert_eq!(restored.collider_handles_by_entity, physics.collider_handles_by_entity);
+        assert_eq!(restored.entities_by_body_handle, physics.entities_by_body_handle);
+        assert_eq!(restored.entities_by_collider_handle, physics.entities_by_collider_handle);
+        assert_eq!(restored.entities_by_joint_handle, physics.entities_by_joint_handle);
+        assert_eq!(restored.one_way_colliders, physics.one_way_colliders);
+        assert_eq!(restored.bodies.len(), physics.bodies.len());
+        assert_eq!(restored.colliders.len(), physics.colliders.len());
+        assert_eq!(restored.impulse_joints.len(), physics.impulse_joints.len());
+    }
+
+    #[test]
+    fn rejects_other_bytes() {
+        assert!(Physics::from_bytes(b"not a snapshot", |_| None).is_err());
+    }
+}
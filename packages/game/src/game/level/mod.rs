@@ -34,4 +34,10 @@ impl LevelRectBundle {
             }),
         }
     }
+
+    /// Lets players jump up through it and drop down through it.
+    pub fn with_one_way(mut self) -> Self {
+        self.collider_options.one_way = true;
+        self
+    }
 }
@@ -3,6 +3,8 @@ pub mod level;
 pub mod player;
 pub mod projectile;
 
+use std::time::Duration;
+
 use bevy::audio::{PlaybackMode, Volume};
 use bevy::prelude::*;
 use bevy_egui::egui::CollapsingHeader;
@@ -13,6 +15,9 @@ use rapier2d::dynamics::IntegrationParameters;
 use core::core_systems;
 use core::event::RollbackEventAppExt;
 use core::physics::collider::PhysicsCollider;
+use core::physics::controller::PhysicsCharacterControllerOptions;
+use core::physics::debug::PhysicsDebug;
+use core::physics::material::PhysicsColliderMaterial;
 use core::physics::*;
 use core::utilities::ggrs::SpawnWithRollbackCommandsExt;
 use core::utilities::hash::transform_hasher;
@@ -26,7 +31,7 @@ use crate::game::player::*;
 use crate::game::projectile::bullet::*;
 use crate::game::projectile::grenade::*;
 use crate::menu::menu_main::goto_main_menu;
-use crate::{GameArgs, GameAssets, GameConfig, State};
+use crate::{GameArgs, GameAssets, GameConfig, Layer, State};
 
 pub trait AddGameAppExt {
     fn add_game(&mut self, fps: usize) -> &mut Self;
@@ -36,6 +41,14 @@ impl AddGameAppExt for App {
     fn add_game(&mut self, fps: usize) -> &mut Self {
         self.add_core::<GameConfig, _>(fps, input_system)
             .stop_sounds_in_background()
+            .insert_resource(PhysicsDebug {
+                layer_colors: vec![
+                    (Layer::Projectile.into(), Color::linear_rgba(1.0, 0.0, 1.0, 0.2)),
+                    (Layer::Player.into(), Color::linear_rgba(0.0, 0.5, 1.0, 0.2)),
+                    (Layer::Wall.into(), Color::linear_rgba(0.0, 1.0, 0.0, 0.2)),
+                ],
+                ..default()
+            })
             //
             .rollback_events::<DamageEvent>()
             //
@@ -68,7 +81,6 @@ impl AddGameAppExt for App {
                     core_systems(),
                     player_system,
                     bullet_system,
-                    grenade_system,
                     grenade_fuse_system,
                 )
                     .run_if(in_state(State::Game)))
@@ -85,6 +97,7 @@ fn setup(
     //
     game_args: Res<GameArgs>,
     game_assets: Res<GameAssets>,
+    controller_options: Res<Assets<PhysicsCharacterControllerOptions>>,
 ) {
     commands.spawn((
         Game {},
@@ -120,13 +133,27 @@ fn setup(
             RotationAngle::Degrees(0.0),
             Vec3::new(-80.0, 0.0, 0.0),
         ));
+        commands.spawn_with_rollback((
+            LevelRectBundle::new(
+                PhysicsCollider::Rectangle { width: 50.0, height: 4.0 },
+                RotationAngle::Degrees(0.0),
+                Vec3::new(0.0, -17.0, 0.0),
+            )
+            .with_one_way(),
+            PhysicsColliderMaterial(game_assets.ice.clone()),
+        ));
     }
 
+    let player_controller = *controller_options
+        .get(&game_assets.player_controller)
+        .expect("Player controller options not loaded");
+
     for handle in 0..game_args.num_players {
         commands.spawn_with_rollback(PlayerBundle::new(
             handle,
             &game_args,
             &game_assets,
+            player_controller,
         ));
     }
 }
@@ -137,6 +164,7 @@ fn update(
     frame: Res<RollbackFrameCount>,
     checksum: Res<Checksum>,
     mut game_args: ResMut<GameArgs>,
+    mut physics_debug: ResMut<PhysicsDebug>,
     mut next_state: ResMut<NextState<State>>,
 ) {
     egui::Window::new("Debugger").show(contexts.ctx_mut(), |ui| {
@@ -151,6 +179,24 @@ fn update(
                 ui.label(format!("Frame {}", frame.0));
                 ui.label(format!("Checksum {}", checksum.0));
             });
+        CollapsingHeader::new("Physics")
+            .default_open(true)
+            .show(ui, |ui| {
+                let hotkey = physics_debug.hotkey;
+
+                ui.checkbox(
+                    &mut physics_debug.enabled,
+                    format!("Debug ({:?})", hotkey),
+                );
+                ui.add_enabled_ui(physics_debug.enabled, |ui| {
+                    ui.checkbox(&mut physics_debug.shapes, "Shapes");
+                    ui.checkbox(&mut physics_debug.sensors, "Sensors");
+                    ui.checkbox(&mut physics_debug.aabbs, "AABBs");
+                    ui.checkbox(&mut physics_debug.velocities, "Velocities");
+                    ui.checkbox(&mut physics_debug.contacts, "Contacts");
+                    ui.checkbox(&mut physics_debug.controllers, "Controllers");
+                });
+            });
 
         if ui.button("Back to main menu").clicked() {
             goto_main_menu(&mut next_state);
@@ -183,17 +229,21 @@ pub fn goto_game(
     session: Session<GameConfig>,
     local_players: LocalPlayers,
 ) {
-    let fps = args.fps as f32;
+    let physics_fps = args.physics_fps.unwrap_or(args.fps) as f64;
+    let substeps = args.substeps.max(1);
+    let dt = (1.0 / physics_fps / substeps as f64) as f32;
 
     commands.insert_resource(session);
     commands.insert_resource(local_players);
     commands.insert_resource(Scaler::default());
     commands.insert_resource(Physics {
         integration_parameters: IntegrationParameters {
-            dt: 1.0 / fps,
-            min_ccd_dt: 1.0 / fps / 100.0,
+            dt,
+            min_ccd_dt: dt / 100.0,
             ..default()
         },
+        timestep: Duration::from_secs_f64(1.0 / physics_fps),
+        substeps,
         ..default()
     });
     next_state.set(State::Game);
@@ -9,7 +9,7 @@ use core::physics::controller::PhysicsCharacterController;
 use core::utilities::ggrs::SpawnWithRollbackCommandsExt;
 use core::utilities::maths::move_towards;
 
-use crate::game::input::{INPUT_LEFT, INPUT_RIGHT, INPUT_SHOOT, INPUT_THROW, INPUT_UP};
+use crate::game::input::{INPUT_DOWN, INPUT_LEFT, INPUT_RIGHT, INPUT_SHOOT, INPUT_THROW, INPUT_UP};
 use crate::game::player::{Direction, Player, PlayerFsm, PlayerState};
 use crate::game::projectile::bullet::BulletBundle;
 use crate::game::projectile::grenade::GrenadeBundle;
@@ -128,6 +128,11 @@ impl Player {
             self.apply_jump(args, JUMP_STRENGTH);
             return;
         }
+        if self.can_drop(args) && args.input.is_set(INPUT_DOWN) {
+            self.set_state(PlayerState::Fall, args);
+            self.apply_drop(args);
+            return;
+        }
         if self.only_left(args) || self.only_right(args) {
             self.set_state(PlayerState::Walk, args);
             return;
@@ -168,6 +173,11 @@ impl Player {
             self.apply_jump(args, JUMP_STRENGTH);
             return;
         }
+        if self.can_drop(args) && args.input.is_set(INPUT_DOWN) {
+            self.set_state(PlayerState::Fall, args);
+            self.apply_drop(args);
+            return;
+        }
         if self.can_shoot(args) && args.input.is_set(INPUT_SHOOT) {
             self.set_state(PlayerState::Shoot, args);
             return;
@@ -404,6 +414,10 @@ impl Player {
         args.controller.is_on_floor()
     }
 
+    fn can_drop(&self, args: &mut PlayerArgs) -> bool {
+        args.controller.is_on_one_way_floor()
+    }
+
     fn can_shoot(&self, _: &mut PlayerArgs) -> bool {
         self.shoot_clock.is_finished()
     }
@@ -435,6 +449,10 @@ impl Player {
         args.controller.velocity.y = strength;
     }
 
+    fn apply_drop(&self, args: &mut PlayerArgs) {
+        args.controller.drop_through();
+    }
+
     fn apply_wall_bump(&self, args: &mut PlayerArgs) {
         args.controller.velocity.x = 0.0;
     }
@@ -448,8 +466,8 @@ impl Player {
     fn apply_gravity(&self, args: &mut PlayerArgs) {
         args.controller.velocity.y = move_towards(
             args.controller.velocity.y,
-            GRAVITY_MAX_SPEED,
-            GRAVITY_ACCELERATION,
+            GRAVITY_MAX_SPEED * args.controller.gravity_scale,
+            GRAVITY_ACCELERATION * args.controller.gravity_scale,
         );
     }
 
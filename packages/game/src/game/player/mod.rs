@@ -15,7 +15,7 @@ use core::event::events::RollbackEvents;
 use core::input::CoreInput;
 use core::physics::body::PhysicsBody;
 use core::physics::collider::{PhysicsCollider, PhysicsColliderOptions};
-use core::physics::controller::PhysicsCharacterController;
+use core::physics::controller::{PhysicsCharacterController, PhysicsCharacterControllerOptions};
 use core::utilities::cmp::cmp_rollback;
 use core::utilities::maths::*;
 
@@ -23,6 +23,8 @@ use crate::game::player::fsm::PlayerArgs;
 use crate::game::Game;
 use crate::{GameArgs, GameAssets, GameConfig, Layer};
 
+const ANCHOR: Vec2 = Vec2::new(0.0, -0.25);
+
 #[derive(Eq, Hash, Copy, Clone, Default, PartialEq)]
 pub enum Direction {
     #[default]
@@ -103,7 +105,12 @@ pub struct PlayerBundle {
 }
 
 impl PlayerBundle {
-    pub fn new(handle: usize, game_args: &GameArgs, game_assets: &GameAssets) -> Self {
+    pub fn new(
+        handle: usize,
+        game_args: &GameArgs,
+        game_assets: &GameAssets,
+        controller_options: PhysicsCharacterControllerOptions,
+    ) -> Self {
         Self {
             game: default(),
             stats: default(),
@@ -116,16 +123,16 @@ impl PlayerBundle {
             },
             //
             body: PhysicsBody::KinematicPositionBased,
-            collider: PhysicsCollider::Rectangle { width: 14.0, height: 32.0 },
+            collider: PhysicsCollider::Capsule { radius: 7.0, height: 32.0 },
             collider_options: PhysicsColliderOptions::from_collision_groups(InteractionGroups {
                 filter: Layer::Wall.into(),
                 memberships: Layer::Wall.into(),
             }),
-            character_controller: default(),
+            character_controller: PhysicsCharacterController::from_options(controller_options),
             //
             sprite: Sprite {
                 image: game_assets.player.clone(),
-                anchor: Anchor::Custom(Vec2::new(0.0, -0.25)),
+                anchor: Anchor::Custom(ANCHOR),
                 texture_atlas: Some(TextureAtlas {
                     index: 0,
                     layout: game_assets.player_atlas_layout.clone(),
@@ -197,6 +204,13 @@ pub fn player_system(
             controller: &mut controller,
             translation: &transform.translation,
         });
+
+        // Players under inverted gravity are drawn upside down.
+        sprite.flip_y = controller.up.y < 0.0;
+        sprite.anchor = Anchor::Custom(match sprite.flip_y {
+            true => -ANCHOR,
+            false => ANCHOR,
+        });
     }
     damage_events.clear();
 }
@@ -9,6 +9,7 @@ use core::clock::TimeToLive;
 use core::event::events::RollbackEvents;
 use core::physics::body::{PhysicsBody, PhysicsBodyOptions, PhysicsBodyVelocity};
 use core::physics::collider::{PhysicsCollider, PhysicsColliderHandle, PhysicsColliderOptions};
+use core::physics::material::PhysicsColliderMaterial;
 use core::physics::Physics;
 use core::utilities::cmp::cmp_rollback;
 
@@ -34,6 +35,7 @@ pub struct BulletBundle {
     body_velocity: PhysicsBodyVelocity,
     collider: PhysicsCollider,
     collider_options: PhysicsColliderOptions,
+    collider_material: PhysicsColliderMaterial,
     //
     sprite: Sprite,
     animator: SpriteSheetAnimator,
@@ -59,14 +61,13 @@ impl BulletBundle {
             //
             collider: PhysicsCollider::Circle { radius: 0.1 },
             collider_options: PhysicsColliderOptions {
-                friction: 0.0,
-                restitution: 0.0,
                 collision_groups: InteractionGroups {
                     filter: Layer::Wall.into(),
                     memberships: Layer::Projectile.into(),
                 },
                 ..default()
             },
+            collider_material: PhysicsColliderMaterial(game_assets.bullet_material.clone()),
             //
             sprite: Sprite {
                 image: game_assets.bullet.clone(),
@@ -88,7 +89,7 @@ impl BulletBundle {
 
 pub fn bullet_system(
     bullets: Query<(Entity, &Rollback, &Bullet, &PhysicsColliderHandle)>,
-    healths: Query<(Entity, &Rollback, &Health, &PhysicsColliderHandle)>,
+    healths: Query<&Health>,
     mut commands: Commands,
     //
     order: Res<RollbackOrdered>,
@@ -99,9 +100,6 @@ pub fn bullet_system(
     let mut bullets = bullets.iter().collect::<Vec<_>>();
     bullets.sort_by(|(_, rollback_a, ..), (_, rollback_b, ..)| cmp_rollback(&order, rollback_a, rollback_b));
 
-    let mut healths = healths.iter().collect::<Vec<_>>();
-    healths.sort_by(|(_, rollback_a, ..), (_, rollback_b, ..)| cmp_rollback(&order, rollback_a, rollback_b));
-
     for (e, _, bullet, collider_handle) in bullets {
         let collider = physics
             .colliders
@@ -115,14 +113,14 @@ pub fn bullet_system(
             collider.shape(),
             QueryFilter::default().exclude_collider(collider_handle.handle()),
             |hit_handle| {
-                if let Some((target, ..)) = healths
-                    .iter()
-                    .find(|(_, _, _, target_handle)| hit_handle == target_handle.handle())
+                if let Some(target) = physics
+                    .collider_entity(hit_handle)
+                    .filter(|target| healths.contains(*target))
                 {
                     commands.entity(e).despawn();
                     damage_events.push(DamageEvent {
                         amount: 1,
-                        target: *target,
+                        target,
                         instigator: bullet.owner,
                     });
                     return true;
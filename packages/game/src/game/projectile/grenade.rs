@@ -4,8 +4,9 @@ use ggrs::PlayerHandle;
 use rapier2d::geometry::{Group, InteractionGroups};
 
 use core::clock::Clock;
-use core::physics::body::{PhysicsBody, PhysicsBodyOptions, PhysicsBodyVelocity};
-use core::physics::collider::{PhysicsCollider, PhysicsColliderHandle, PhysicsColliderOptions};
+use core::physics::body::{PhysicsBody, PhysicsBodyOptions, PhysicsImpulse};
+use core::physics::collider::{PhysicsCollider, PhysicsColliderOptions};
+use core::physics::material::PhysicsColliderMaterial;
 use core::utilities::cmp::cmp_rollback;
 
 use crate::game::player::{Direction, Player};
@@ -28,9 +29,10 @@ pub struct GrenadeBundle {
     //
     body: PhysicsBody,
     body_options: PhysicsBodyOptions,
-    body_velocity: PhysicsBodyVelocity,
+    body_impulse: PhysicsImpulse,
     collider: PhysicsCollider,
     collider_options: PhysicsColliderOptions,
+    collider_material: PhysicsColliderMaterial,
     //
     sprite: Sprite,
     transform: Transform,
@@ -52,27 +54,26 @@ impl GrenadeBundle {
                 angular_damping: 10.0,
                 ..default()
             },
-            body_velocity: PhysicsBodyVelocity {
-                linear_velocity: Some(match player.direction {
+            body_impulse: PhysicsImpulse::from_velocity_change(
+                match player.direction {
                     Direction::Left => Vec2::new(-LINEAR_IMPULSE.x, LINEAR_IMPULSE.y),
                     Direction::Right => Vec2::new(LINEAR_IMPULSE.x, LINEAR_IMPULSE.y),
-                }),
-                angular_velocity: Some(match player.direction {
+                },
+                match player.direction {
                     Direction::Left => ANGULAR_IMPULSE,
                     Direction::Right => -ANGULAR_IMPULSE,
-                }),
-            },
+                },
+            ),
             //
             collider: PhysicsCollider::Circle { radius: 3.5 },
             collider_options: PhysicsColliderOptions {
-                friction: 2.0,
-                restitution: 0.6,
                 collision_groups: InteractionGroups {
                     filter: Layer::Wall.into(),
                     memberships: Into::<Group>::into(Layer::Wall) | Layer::Projectile.into(),
                 },
                 ..default()
             },
+            collider_material: PhysicsColliderMaterial(game_assets.rubber.clone()),
             //
             sprite: Sprite {
                 image: game_assets.grenade.clone(),
@@ -87,28 +88,6 @@ impl GrenadeBundle {
     }
 }
 
-pub fn grenade_system(
-    grenades: Query<
-        (
-            Entity,
-            &Rollback,
-            &Grenade,
-            &PhysicsColliderHandle,
-        ),
-        With<PhysicsBodyVelocity>,
-    >,
-    mut commands: Commands,
-    //
-    order: Res<RollbackOrdered>,
-) {
-    let mut grenades = grenades.iter().collect::<Vec<_>>();
-    grenades.sort_by(|(_, rollback_a, ..), (_, rollback_b, ..)| cmp_rollback(&order, rollback_a, rollback_b));
-
-    for (e, ..) in grenades {
-        commands.entity(e).remove::<PhysicsBodyVelocity>();
-    }
-}
-
 pub fn grenade_fuse_system(
     mut grenades: Query<(Entity, &Rollback, &mut Grenade)>,
     mut commands: Commands,
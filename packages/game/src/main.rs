@@ -8,12 +8,15 @@ use bevy_common_assets::ron::RonAssetPlugin;
 use bevy_egui::EguiPlugin;
 use bevy_ggrs::ggrs::Config;
 use bevy_matchbox::matchbox_socket::PeerId;
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
 use rapier2d::geometry::Group;
 
 use core::anim::SpriteSheetAnimation;
 use core::input::CoreInput;
 use core::loader::CoreDynamicAssetCollection;
+use core::physics::controller::PhysicsCharacterControllerOptions;
+use core::physics::material::PhysicsMaterial;
 
 use crate::game::AddGameAppExt;
 use crate::menu::menu_local::AddLocalMenuAppExt;
@@ -65,6 +68,10 @@ pub struct GameArgs {
 
     #[clap(long, default_value = "60")]
     pub fps: usize,
+    #[clap(long)]
+    pub physics_fps: Option<usize>,
+    #[clap(long, default_value = "1")]
+    pub substeps: usize,
     #[clap(long, default_value = "2")]
     pub num_players: usize,
     #[clap(long, default_value = "2")]
@@ -117,6 +124,15 @@ pub struct GameAssets {
     pub player_dead_bounce: Handle<SpriteSheetAnimation>,
     #[asset(key = "player_atlas_layout")]
     pub player_atlas_layout: Handle<TextureAtlasLayout>,
+    #[asset(key = "player_controller")]
+    pub player_controller: Handle<PhysicsCharacterControllerOptions>,
+
+    #[asset(key = "material_ice")]
+    pub ice: Handle<PhysicsMaterial>,
+    #[asset(key = "material_rubber")]
+    pub rubber: Handle<PhysicsMaterial>,
+    #[asset(key = "material_bullet")]
+    pub bullet_material: Handle<PhysicsMaterial>,
 
     #[asset(key = "background_music")]
     pub background_music: Handle<AudioSource>,
@@ -136,6 +152,14 @@ fn main() {
     let args = GameArgs::parse();
     let args_fps = args.fps;
 
+    // Frames without a physics tick would drop the movement of character controllers,
+    // and an uneven number of ticks per frame would make them jitter.
+    if args.physics_fps.is_some_and(|physics_fps| physics_fps < args.fps || physics_fps.checked_rem(args.fps) != Some(0)) {
+        GameArgs::command()
+            .error(ErrorKind::ValueValidation, "--physics-fps must be a multiple of --fps")
+            .exit();
+    }
+
     app.add_plugins(
         DefaultPlugins
             .set(ImagePlugin::default_nearest())